@@ -1,16 +1,17 @@
 use super::Bounds;
+use crate::parser::Peaks;
 use egui::util::cache::{ComputerMut, FrameCache};
-use std::collections::BTreeMap;
+use noisy_float::types::n64;
 
 /// Bounded
-pub(super) type Bounded = FrameCache<BTreeMap<u64, u64>, Bounder>;
+pub(super) type Bounded = FrameCache<Peaks, Bounder>;
 
 /// Bounder
 #[derive(Default)]
 pub(super) struct Bounder;
 
-impl ComputerMut<(&BTreeMap<u64, u64>, &Bounds), BTreeMap<u64, u64>> for Bounder {
-    fn compute(&mut self, (peaks, bounds): (&BTreeMap<u64, u64>, &Bounds)) -> BTreeMap<u64, u64> {
+impl ComputerMut<(&Peaks, &Bounds), Peaks> for Bounder {
+    fn compute(&mut self, (peaks, bounds): (&Peaks, &Bounds)) -> Peaks {
         // peaks
         //     .iter()
         //     .filter_map(|(mass, &intensity)| {
@@ -22,8 +23,8 @@ impl ComputerMut<(&BTreeMap<u64, u64>, &Bounds), BTreeMap<u64, u64>> for Bounder
         //     })
         //     .collect()
         let mut peaks = peaks.clone();
-        peaks = peaks.split_off(&bounds.x.start);
-        peaks.split_off(&bounds.x.end);
+        peaks = peaks.split_off(&n64(bounds.x.start));
+        peaks.split_off(&n64(bounds.x.end));
         peaks
     }
 }
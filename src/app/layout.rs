@@ -0,0 +1,134 @@
+use std::ops::Range;
+
+/// Vertical steps (half label heights) tried before giving up on a label
+const STEPS: i32 = 16;
+
+/// Horizontal shifts (half label widths) tried at each step
+const SHIFTS: [i32; 5] = [0, -1, 1, -2, 2];
+
+/// Anchor and size of a label (pixels)
+pub(super) type Label = ((i32, i32), (u32, u32));
+
+/// Offsets of the label attachment points from their anchors, the labels kept
+/// apart above (or below) them
+pub(super) fn layout(
+    labels: &[Label],
+    area: &(Range<i32>, Range<i32>),
+    gap: i32,
+    below: bool,
+) -> Vec<(i32, i32)> {
+    let mut order = (0..labels.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&index| {
+        let y = labels[index].0 .1;
+        if below {
+            -y
+        } else {
+            y
+        }
+    });
+    let mut placed: Vec<Rectangle> = Vec::with_capacity(labels.len());
+    let mut offsets = vec![(0, 0); labels.len()];
+    for index in order {
+        let ((x, y), (width, height)) = labels[index];
+        let (width, height) = (width as i32, height as i32);
+        let candidate = |step: i32, shift: i32| {
+            let distance = gap + step * height / 2;
+            let left = x - width / 2 + shift * width / 2;
+            let top = if below {
+                y + distance
+            } else {
+                y - distance - height
+            };
+            // Inside the area
+            let left = left.clamp(area.0.start, (area.0.end - width).max(area.0.start));
+            let top = top.clamp(area.1.start, (area.1.end - height).max(area.1.start));
+            Rectangle {
+                left,
+                top,
+                right: left + width,
+                bottom: top + height,
+            }
+        };
+        let rectangle = (0..=STEPS)
+            .flat_map(|step| SHIFTS.map(|shift| (step, shift)))
+            .map(|(step, shift)| candidate(step, shift))
+            .find(|candidate| placed.iter().all(|placed| !placed.overlaps(candidate)))
+            .unwrap_or_else(|| candidate(0, 0));
+        let attachment = if below {
+            rectangle.top
+        } else {
+            rectangle.bottom
+        };
+        offsets[index] = ((rectangle.left + rectangle.right) / 2 - x, attachment - y);
+        placed.push(rectangle);
+    }
+    offsets
+}
+
+/// Label box
+#[derive(Clone, Copy, Debug)]
+struct Rectangle {
+    left: i32,
+    top: i32,
+    right: i32,
+    bottom: i32,
+}
+
+impl Rectangle {
+    fn overlaps(&self, other: &Self) -> bool {
+        self.left < other.right
+            && other.left < self.right
+            && self.top < other.bottom
+            && other.top < self.bottom
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AREA: (Range<i32>, Range<i32>) = (0..200, 0..100);
+
+    #[test]
+    fn natural() {
+        let offsets = layout(
+            &[((50, 50), (20, 10)), ((150, 80), (20, 10))],
+            &AREA,
+            2,
+            false,
+        );
+        assert_eq!(offsets, [(0, -2), (0, -2)]);
+        let offsets = layout(&[((50, 50), (20, 10))], &AREA, 2, true);
+        assert_eq!(offsets, [(0, 2)]);
+    }
+
+    #[test]
+    fn collisions() {
+        // The higher anchor keeps its place, the other one moves away
+        let offsets = layout(
+            &[((50, 55), (20, 10)), ((55, 50), (20, 10))],
+            &AREA,
+            2,
+            false,
+        );
+        assert_eq!(offsets[1], (0, -2));
+        assert_ne!(offsets[0], (0, -2));
+        let rectangle = |((x, y), (dx, dy)): ((i32, i32), (i32, i32))| Rectangle {
+            left: x + dx - 10,
+            top: y + dy - 10,
+            right: x + dx + 10,
+            bottom: y + dy,
+        };
+        assert!(!rectangle(((50, 55), offsets[0])).overlaps(&rectangle(((55, 50), offsets[1]))));
+    }
+
+    #[test]
+    fn axes() {
+        let offsets = layout(&[((2, 5), (20, 10))], &AREA, 2, false);
+        assert_eq!(offsets, [(8, 5)]);
+        let offsets = layout(&[((100, 50), (20, 10))], &AREA, 60, false);
+        assert_eq!(offsets, [(0, -40)]);
+        let offsets = layout(&[((100, 99), (20, 10))], &AREA, 2, true);
+        assert_eq!(offsets, [(0, -9)]);
+    }
+}
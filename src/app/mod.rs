@@ -1,13 +1,27 @@
 use self::{
     bounder::Bounded,
-    config::{Bounds, Config, Descriptions},
+    config::{Arrangement, Bounds, Config, Descriptions},
+    layout::layout,
     normalizer::Normalized,
 };
 use crate::{
-    parser::Parsed,
-    utils::{with_index, BoundExt, Display, DroppedFileExt, RangeBoundsExt, UiExt},
+    clustering::{cluster, Dendrogram, Linkage},
+    interpretation::{picolinyl, Chain, Interpretation, BASE_IONS},
+    library::{search, Hit, Library},
+    losses::loss,
+    operations::{average, subtract, Scale},
+    parser::{self, parse_library_with, Mode, Parsed},
+    picking::Method,
+    similarity::{self, Metric, Similarity},
+    template::{Entry, Templates, LIPID, MOLECULAR_ION},
+    theoretical::{compare, Comparison, Derivative, FattyAcid},
+    utils::{
+        decode, with_index, BoundExt, Display, DroppedFileExt, FloatExt, RangeBoundsExt,
+        SelectableValueFromIter, UiExt, ENCODINGS,
+    },
+    writer::{Scaling, Separator, Writer},
 };
-use anyhow::{anyhow, Context as _, Error, Result};
+use anyhow::{anyhow, bail, Context as _, Error, Result};
 use eframe::{
     epaint::Hsva,
     get_value,
@@ -19,38 +33,63 @@ use egui::{
     menu::bar,
     plot::{
         self, log_grid_spacer, uniform_grid_spacer, Bar, BarChart, CoordinatesFormatter, Corner,
-        HLine, Legend, Line as DLine, MarkerShape, Plot, PlotBounds, PlotPoint, PlotPoints, Points,
-        Text, VLine,
+        Legend, MarkerShape, Plot, PlotBounds, PlotPoint, PlotPoints, Points, Text,
     },
-    text::LayoutJob,
-    warn_if_debug_build, Align, Align2, CentralPanel, Color32, ColorImage, Context, DragValue,
-    DroppedFile, FontData, FontDefinitions, FontFamily, FontId, Id, LayerId, Layout, Order,
-    Response, RichText, ScrollArea, SidePanel, Slider, TextEdit, TextStyle, TopBottomPanel, Ui,
-    Vec2, WidgetText, Window,
+    text::{LayoutJob, TextFormat},
+    warn_if_debug_build, Align, Align2, Button, CentralPanel, Color32, ColorImage, ComboBox,
+    Context, DragValue, DroppedFile, FontData, FontDefinitions, FontFamily, FontId, Grid, Id,
+    LayerId, Layout, Order, Response, RichText, ScrollArea, SidePanel, Slider, Stroke, TextEdit,
+    TextStyle, TopBottomPanel, Ui, Vec2, WidgetText, Window,
 };
 use egui_extras::RetainedImage;
+use encoding_rs::Encoding;
 use image::{imageops::*, ColorType, DynamicImage, GrayImage, ImageResult, RgbaImage};
 use indexmap::{indexmap, IndexMap};
-use ndarray::{Array1, Dimension};
+use itertools::Itertools;
+use ndarray::{Array1, Array2, Dimension};
 use ndarray_stats::{interpolate::Linear, Quantile1dExt};
-use noisy_float::types::n64;
+use noisy_float::types::{n64, N64};
 use plotters::{
     backend::{PixelFormat, RGBPixel},
+    coord::{ranged1d::ValueFormatter, Shift},
     prelude::*,
-    style::RelativeSize,
+    style::{
+        text_anchor::{HPos, Pos, VPos},
+        RelativeSize,
+    },
 };
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     default::default,
     fmt::{self, Write},
+    mem,
     ops::{Bound, Deref, DerefMut, Range, RangeBounds, RangeInclusive},
     path::{Path, PathBuf},
 };
 use tracing::{error, info};
+use uom::si::time::minute;
 
 const COLOR: Color32 = Color32::BLACK;
 
+/// Heatmap size
+const HEATMAP: (u32, u32) = (800, 800);
+
+/// Longest spectrum name on the heatmap axes (characters)
+const HEATMAP_LABEL: usize = 16;
+
+/// Width of the error bar whiskers (pixels)
+const ERROR_BAR_WIDTH: u32 = 6;
+
+/// Gap between the peaks and their labels (pixels)
+const LABEL_GAP: i32 = 4;
+
+/// Label text at its anchor (data coordinates)
+type Anchored<X, Y> = ((X, Y), String);
+
+/// Radius of the diagnostic ion marks (pixels)
+const DIAGNOSTIC_ION_SIZE: i32 = 3;
+
 macro font($fonts: ident, $name: literal) {
     $fonts.font_data.insert(
         $name.to_owned(),
@@ -101,6 +140,35 @@ pub fn color(index: usize) -> Color32 {
     Hsva::new(h, 0.85, 0.5, 1.0).into()
 }
 
+fn rgb_color(color: Color32) -> RGBColor {
+    RGBColor(color.r(), color.g(), color.b())
+}
+
+/// Caption of the spectrum: its lipid shorthand and retention time, if
+/// extracted from the filename, its name otherwise
+fn caption(parsed: &Parsed) -> String {
+    let Some(lipid) = parsed.metadata.get(LIPID) else {
+        return parsed.name.clone();
+    };
+    match parsed.retention_time {
+        Some(retention_time) => format!("{lipid} ({:.3} min)", retention_time.get::<minute>()),
+        None => lipid.to_owned(),
+    }
+}
+
+/// Molecular ion extracted from the filename
+fn molecular_ion(parsed: &Parsed) -> Option<u64> {
+    parsed.metadata.get(MOLECULAR_ION)?.parse().ok()
+}
+
+/// Writes the file, unless it exists and isn't to be overwritten
+fn save(path: &Path, contents: impl AsRef<[u8]>, overwrite: bool) -> Result<()> {
+    if !overwrite && path.exists() {
+        bail!("{} already exists", path.display());
+    }
+    std::fs::write(path, contents).with_context(|| path.display().to_string())
+}
+
 fn save_image(image: &ColorImage, path: &Path) -> ImageResult<()> {
     let height = image.height();
     let width = image.width();
@@ -124,6 +192,40 @@ fn save_image(image: &ColorImage, path: &Path) -> ImageResult<()> {
     // luma8.save(path)
 }
 
+/// Offending line of the parse error with the failing span highlighted
+fn snippet(ui: &Ui, error: &parser::Error) -> LayoutJob {
+    let font_id = TextStyle::Monospace.resolve(ui.style());
+    let color = ui.visuals().text_color();
+    let mut job = LayoutJob::default();
+    let gutter = format!("{} | ", error.line);
+    job.append(&gutter, 0.0, TextFormat::simple(font_id.clone(), color));
+    job.append(
+        &error.text[..error.span.start],
+        0.0,
+        TextFormat::simple(font_id.clone(), color),
+    );
+    // An empty span is shown as a single highlighted space
+    let found = match error.found() {
+        "" => " ",
+        found => found,
+    };
+    job.append(
+        found,
+        0.0,
+        TextFormat {
+            background: ui.visuals().error_fg_color.linear_multiply(0.25),
+            underline: Stroke::new(1.0, ui.visuals().error_fg_color),
+            ..TextFormat::simple(font_id.clone(), ui.visuals().error_fg_color)
+        },
+    );
+    job.append(
+        &error.text[error.span.end..],
+        0.0,
+        TextFormat::simple(font_id, color),
+    );
+    job
+}
+
 fn setup_fonts(ctx: &Context) {
     // Start with the default fonts (we will be adding to them rather than replacing them).
     let mut fonts = FontDefinitions::default();
@@ -150,9 +252,21 @@ fn setup_fonts(ctx: &Context) {
 pub struct App {
     #[serde(skip)]
     files: Vec<DroppedFile>,
-    parsed: HashMap<usize, Parsed>,
+    #[serde(skip)]
+    parsed: IndexMap<usize, Parsed>,
+    #[serde(skip)]
+    sources: HashMap<usize, usize>,
+    /// Encoding chosen by the user
+    #[serde(skip)]
+    encodings: HashMap<usize, &'static Encoding>,
+    /// Encoding actually used
+    #[serde(skip)]
+    decoded: HashMap<usize, &'static Encoding>,
+    #[serde(skip)]
     colors: IndexMap<usize, Color32>,
+    #[serde(skip)]
     filter: HashSet<usize>,
+    selected: usize,
 
     left_panel: bool,
 
@@ -171,9 +285,30 @@ pub struct App {
     // visuals: Visuals,
     labels: Vec<Label>,
     points: Vec<Point>,
+    differences: Vec<Difference>,
+    mode: Mode,
+    templates: Templates,
+    similarity: Similarity,
+    writer: Writer,
 
     #[serde(skip)]
     errors: Errors,
+    #[serde(skip)]
+    export: Export,
+    #[serde(skip)]
+    search: Search,
+    #[serde(skip)]
+    heatmap: Heatmap,
+    #[serde(skip)]
+    clustering: Clustering,
+    #[serde(skip)]
+    subtraction: Subtraction,
+    #[serde(skip)]
+    consensus: Consensus,
+    #[serde(skip)]
+    picolinyl: Picolinyl,
+    #[serde(skip)]
+    theoretical: Theoretical,
 }
 
 impl App {
@@ -219,28 +354,117 @@ impl App {
             (!input.raw.dropped_files.is_empty()).then_some(input.raw.dropped_files.clone())
         }) {
             info!(?files);
+            self.clear();
             self.files = files;
-            for (index, file) in self.files.iter().enumerate() {
-                let content = match file.content() {
-                    Ok(content) => content,
-                    Err(error) => {
-                        error!(%error);
-                        self.errors.buffer.insert(index, error);
-                        continue;
-                    }
-                };
-                let parsed = match content.parse() {
-                    Ok(file) => file,
-                    Err(error) => {
-                        error!(%error);
-                        self.errors.buffer.insert(index, error);
-                        continue;
-                    }
-                };
-                self.parsed.insert(index, parsed);
-                self.colors.insert(index, color(index));
+            for index in 0..self.files.len() {
+                self.load(index);
+            }
+        }
+    }
+
+    /// Decodes and parses the file, replacing its spectra in place (their
+    /// indices, colors and exclusions are kept)
+    fn load(&mut self, index: usize) {
+        self.invalidate();
+        let mut spectra = self
+            .parsed
+            .keys()
+            .copied()
+            .filter(|spectrum| self.sources.get(spectrum) == Some(&index))
+            .collect::<Vec<_>>()
+            .into_iter();
+        self.errors.buffer.remove(&index);
+        let content = match self.files[index]
+            .bytes()
+            .and_then(|bytes| decode(&bytes, self.encodings.get(&index).copied()))
+        {
+            Ok((content, encoding)) => {
+                self.decoded.insert(index, encoding);
+                content
+            }
+            Err(error) => {
+                error!(%error);
+                self.errors.buffer.entry(index).or_default().push(error);
+                for spectrum in spectra {
+                    self.remove(spectrum);
+                }
+                return;
+            }
+        };
+        // Library (one or more records), named by the file if it has only one
+        let records = parse_library_with(&content, self.mode);
+        let extracted = match self.files[index].stem() {
+            Some(stem) if records.len() == 1 => self.templates.extract(stem),
+            _ => None,
+        };
+        for parsed in records {
+            let mut parsed = match parsed {
+                Ok(parsed) => parsed,
+                Err(error) => {
+                    error!(%error);
+                    self.errors
+                        .buffer
+                        .entry(index)
+                        .or_default()
+                        .push(error.into());
+                    continue;
+                }
+            };
+            if let Some(extracted) = &extracted {
+                extracted.clone().apply(&mut parsed);
+            }
+            match spectra.next() {
+                Some(spectrum) => self.parsed[&spectrum] = parsed,
+                None => {
+                    let spectrum = self.parsed.keys().max().map_or(0, |max| max + 1);
+                    self.parsed.insert(spectrum, parsed);
+                    self.sources.insert(spectrum, index);
+                    self.colors.insert(spectrum, color(spectrum));
+                }
             }
         }
+        for spectrum in spectra {
+            self.remove(spectrum);
+        }
+    }
+
+    /// Removes the spectrum, keeping the order of the others
+    fn remove(&mut self, spectrum: usize) {
+        self.parsed.shift_remove(&spectrum);
+        self.sources.remove(&spectrum);
+        self.colors.shift_remove(&spectrum);
+        self.filter.remove(&spectrum);
+        if self.search.opened == Some(spectrum) {
+            self.search.opened = None;
+        }
+        self.search.hits.clear();
+        self.invalidate();
+    }
+
+    /// Removes the files and all the spectra
+    fn clear(&mut self) {
+        self.files.clear();
+        self.encodings.clear();
+        self.decoded.clear();
+        self.errors.buffer.clear();
+        self.parsed.clear();
+        self.sources.clear();
+        self.colors.clear();
+        self.filter.clear();
+        self.selected = 0;
+        self.search.opened = None;
+        self.search.hits.clear();
+        self.invalidate();
+    }
+
+    /// Forgets the results computed on the spectra, once they change
+    fn invalidate(&mut self) {
+        self.heatmap.spectra.clear();
+        self.heatmap.matrix = Array2::zeros((0, 0));
+        self.clustering.spectra.clear();
+        self.clustering.dendrogram = Dendrogram::default();
+        self.picolinyl.interpretation = None;
+        self.theoretical.comparison = None;
     }
 
     fn bottom_panel(&mut self, ctx: &Context) {
@@ -257,16 +481,21 @@ impl App {
 
     fn central_panel(&mut self, ctx: &Context) {
         CentralPanel::default().show(ctx, |ui| {
-            if self.files.is_empty() {
-                ui.centered_and_justified(|ui| ui.label("Drag and drop .msp file"));
-            } else {
-                ui.vertical_centered_justified(|ui| {
-                    ui.heading(&self.parsed[&0].name);
-                });
-                ui.separator();
+            if !self.parsed.is_empty() {
+                if let (Arrangement::Single, Some(parsed)) = (
+                    self.config.chart.arrangement,
+                    self.parsed.get(&self.selected),
+                ) {
+                    ui.vertical_centered_justified(|ui| {
+                        ui.heading(&parsed.name);
+                    });
+                    ui.separator();
+                }
                 let desired_size = ui.available_size();
                 let rgb = self.rgb(ui).unwrap().show_size(ui, desired_size);
                 // let svg = self.svg(ui).unwrap().show_size(ui, desired_size);
+            } else {
+                ui.centered_and_justified(|ui| ui.label("Drag and drop .msp file"));
             }
         });
     }
@@ -294,29 +523,156 @@ impl App {
                                 ui.label("Mass:");
                                 ui.add(
                                     DragValue::new(&mut self.config.bounds.x.start)
-                                        .clamp_range(0..=self.config.bounds.x.end)
+                                        .clamp_range(0.0..=self.config.bounds.x.end)
                                         .speed(1),
                                 );
                                 ui.add(
                                     DragValue::new(&mut self.config.bounds.x.end)
-                                        .clamp_range(self.config.bounds.x.start..=u64::MAX)
+                                        .clamp_range(self.config.bounds.x.start..=f64::MAX)
                                         .speed(1),
                                 );
+                                if ui
+                                    .button("Fit")
+                                    .on_hover_text(
+                                        "Fit the largest molecular ion (or mass) of the included \
+                                         spectra",
+                                    )
+                                    .clicked()
+                                {
+                                    if let Some(end) = self.fit() {
+                                        self.config.bounds.x.end =
+                                            (end as f64).max(self.config.bounds.x.start);
+                                    }
+                                }
                             });
                             ui.horizontal(|ui| {
                                 ui.label("Intensity:");
                                 ui.add(
                                     DragValue::new(&mut self.config.bounds.y.start)
-                                        .clamp_range(0..=self.config.bounds.y.end)
+                                        .clamp_range(0.0..=self.config.bounds.y.end)
                                         .speed(1),
                                 );
                                 ui.add(
                                     DragValue::new(&mut self.config.bounds.y.end)
-                                        .clamp_range(self.config.bounds.y.start..=100)
+                                        .clamp_range(self.config.bounds.y.start..=100.0)
                                         .speed(1),
                                 );
                             });
                         });
+                        ui.checkbox(&mut self.config.chart.annotations, "Annotations")
+                            .on_hover_text("Peak annotations above the bars");
+                        ui.checkbox(&mut self.config.chart.peak_labels, "Labels")
+                            .on_hover_text("m/z labels above the picked peaks");
+                        ui.add_enabled_ui(self.config.chart.peak_labels, |ui| {
+                            let picker = &mut self.config.chart.picker;
+                            ui.horizontal(|ui| {
+                                ui.label("Peaks:");
+                                for value in [
+                                    Method::All,
+                                    Method::Maximums,
+                                    Method::ZScore {
+                                        lag: 5,
+                                        threshold: 3.5,
+                                        influence: 0.5,
+                                    },
+                                ] {
+                                    let selected = mem::discriminant(&picker.method)
+                                        == mem::discriminant(&value);
+                                    if ui.selectable_label(selected, value.to_string()).clicked()
+                                        && !selected
+                                    {
+                                        picker.method = value;
+                                    }
+                                }
+                            });
+                            if let Method::ZScore {
+                                lag,
+                                threshold,
+                                influence,
+                            } = &mut picker.method
+                            {
+                                ui.horizontal(|ui| {
+                                    ui.add(DragValue::new(lag).clamp_range(1..=100).speed(1))
+                                        .on_hover_text("Lag");
+                                    ui.add(
+                                        DragValue::new(threshold)
+                                            .clamp_range(0.0..=f64::MAX)
+                                            .speed(0.1),
+                                    )
+                                    .on_hover_text("Threshold (standard deviations)");
+                                    ui.add(
+                                        DragValue::new(influence)
+                                            .clamp_range(0.0..=1.0)
+                                            .speed(0.01),
+                                    )
+                                    .on_hover_text("Influence");
+                                });
+                            }
+                            ui.horizontal(|ui| {
+                                ui.label("Threshold:");
+                                ui.add(
+                                    DragValue::new(&mut picker.threshold)
+                                        .clamp_range(0.0..=1.0)
+                                        .speed(0.01),
+                                )
+                                .on_hover_text("Relative to the base peak");
+                                ui.label("Count:");
+                                ui.add(DragValue::new(&mut picker.count).speed(1))
+                                    .on_hover_text("0 for all peaks");
+                            });
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Arrangement:");
+                            let other = self
+                                .parsed
+                                .keys()
+                                .copied()
+                                .find(|&index| index != self.selected)
+                                .unwrap_or(self.selected);
+                            let arrangement = &mut self.config.chart.arrangement;
+                            for value in [
+                                Arrangement::Single,
+                                Arrangement::Overlay,
+                                Arrangement::Stack,
+                                Arrangement::Waterfall { x: 0.0, y: 10.0 },
+                                Arrangement::Mirror(other),
+                            ] {
+                                let selected =
+                                    mem::discriminant(arrangement) == mem::discriminant(&value);
+                                if ui.selectable_label(selected, value.to_string()).clicked()
+                                    && !selected
+                                {
+                                    *arrangement = value;
+                                }
+                            }
+                        });
+                        match &mut self.config.chart.arrangement {
+                            Arrangement::Waterfall { x, y } => {
+                                ui.horizontal(|ui| {
+                                    ui.label("Offset:");
+                                    ui.add(DragValue::new(x).speed(0.1)).on_hover_text("m/z");
+                                    ui.add(DragValue::new(y).speed(0.1))
+                                        .on_hover_text("Intensity %");
+                                });
+                            }
+                            Arrangement::Mirror(other) => {
+                                ui.horizontal(|ui| {
+                                    ui.label("Compare with:");
+                                    ComboBox::from_id_source("mirror")
+                                        .selected_text(
+                                            self.parsed
+                                                .get(other)
+                                                .map_or("", |parsed| &parsed.name),
+                                        )
+                                        .show_ui(ui, |ui| {
+                                            for (&index, parsed) in &self.parsed {
+                                                ui.selectable_value(other, index, &parsed.name);
+                                            }
+                                        });
+                                });
+                            }
+                            _ => {}
+                        }
                         // ui.label("Mesh:");
                         ui.group(|ui| {
                             ui.label("Descriptions:");
@@ -451,6 +807,7 @@ impl App {
                                         }
                                     });
                                 ui.toggle_value(&mut label.bold, "bold");
+                                label.coordinates.ui(ui);
                                 !ui.button(RichText::new("-").monospace()).clicked()
                             })
                             .inner
@@ -490,6 +847,7 @@ impl App {
                                     .on_hover_text("Radius");
                                 ui.color_edit_button_srgba(&mut point.color)
                                     .on_hover_text("Color");
+                                point.coordinates.ui(ui);
                                 !ui.button(RichText::new("-").monospace()).clicked()
                             })
                             .inner
@@ -500,6 +858,564 @@ impl App {
                             }
                         });
                     });
+                    // Differences
+                    ui.collapsing(WidgetText::from("Differences").heading(), |ui| {
+                        self.differences.retain_mut(|difference| {
+                            ui.horizontal(|ui| {
+                                ui.label("Δ:");
+                                ui.add(DragValue::new(&mut difference.from).speed(1))
+                                    .on_hover_text("From m/z");
+                                ui.add(DragValue::new(&mut difference.to).speed(1))
+                                    .on_hover_text("To m/z");
+                                for line in [Line::Horizontal, Line::Bracket, Line::Arrow] {
+                                    ui.selectable_value(&mut difference.line, line, line.symbol())
+                                        .on_hover_text(line.to_string());
+                                }
+                                let suggestion = loss(difference.to - difference.from);
+                                let width = 4.0 * ui.text_style_height(&TextStyle::Body);
+                                TextEdit::singleline(&mut difference.text)
+                                    .hint_text(suggestion.unwrap_or_default())
+                                    .desired_width(width)
+                                    .show(ui);
+                                if ui
+                                    .add_enabled(suggestion.is_some(), Button::new("⟲"))
+                                    .on_hover_text("Common neutral loss")
+                                    .clicked()
+                                {
+                                    difference.text = suggestion.unwrap_or_default().to_owned();
+                                }
+                                !ui.button(RichText::new("-").monospace()).clicked()
+                            })
+                            .inner
+                        });
+                        ui.horizontal(|ui| {
+                            if ui.button(RichText::new("+").monospace()).clicked() {
+                                self.differences.push(default());
+                            }
+                        });
+                    });
+                    // Import
+                    ui.collapsing(WidgetText::from("Import").heading(), |ui| {
+                        let mut lenient = self.mode == Mode::Lenient;
+                        if ui
+                            .checkbox(&mut lenient, "Lenient")
+                            .on_hover_text("Repair malformed records, with warnings")
+                            .changed()
+                        {
+                            self.mode = if lenient { Mode::Lenient } else { Mode::Strict };
+                            for index in 0..self.files.len() {
+                                self.load(index);
+                            }
+                        }
+                        ui.label("Filename templates:").on_hover_text(
+                            "Fields: {carbons}, {double_bonds}, {positions}, {molecular_ion}, \
+                             {retention_time}, {replicate}, {_}",
+                        );
+                        self.templates.0.retain_mut(|entry| {
+                            ui.horizontal(|ui| {
+                                let response = ui.text_edit_singleline(&mut entry.text);
+                                if response.changed() {
+                                    entry.update();
+                                }
+                                if let Some(error) = entry.error() {
+                                    response.on_hover_text(error);
+                                    ui.colored_label(ui.visuals().error_fg_color, "⚠");
+                                }
+                                !ui.button(RichText::new("-").monospace()).clicked()
+                            })
+                            .inner
+                        });
+                        ui.horizontal(|ui| {
+                            if ui.button(RichText::new("+").monospace()).clicked() {
+                                self.templates.0.push(Entry::new(String::new()));
+                            }
+                            if ui
+                                .button("Apply")
+                                .on_hover_text("Reload the files with the templates")
+                                .clicked()
+                            {
+                                for index in 0..self.files.len() {
+                                    self.load(index);
+                                }
+                            }
+                        });
+                    });
+                    // Background
+                    ui.collapsing(WidgetText::from("Background").heading(), |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Background:");
+                            let background = &mut self.subtraction.background;
+                            ComboBox::from_id_source("background")
+                                .selected_text(
+                                    self.parsed
+                                        .get(background)
+                                        .map_or("", |parsed| &parsed.name),
+                                )
+                                .show_ui(ui, |ui| {
+                                    for (&index, parsed) in &self.parsed {
+                                        ui.selectable_value(background, index, &parsed.name);
+                                    }
+                                });
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Scale:");
+                            let scale = &mut self.subtraction.scale;
+                            if ui
+                                .selectable_label(matches!(scale, Scale::Factor(_)), "Factor")
+                                .clicked()
+                            {
+                                *scale = Scale::Factor(1.0);
+                            }
+                            if ui
+                                .selectable_label(matches!(scale, Scale::Ion(_)), "Reference ion")
+                                .clicked()
+                            {
+                                *scale = Scale::Ion(
+                                    self.parsed
+                                        .get(&self.selected)
+                                        .and_then(|parsed| {
+                                            let (&mass, _) = parsed
+                                                .peaks
+                                                .iter()
+                                                .max_by_key(|(_, &intensity)| intensity)?;
+                                            Some(mass)
+                                        })
+                                        .unwrap_or(n64(0.0)),
+                                );
+                            }
+                            match scale {
+                                Scale::Factor(factor) => {
+                                    ui.add(DragValue::new(factor).speed(0.01));
+                                }
+                                Scale::Ion(mass) => {
+                                    let mut value = mass.raw();
+                                    if ui
+                                        .add(DragValue::new(&mut value).speed(1.0))
+                                        .on_hover_text("m/z")
+                                        .changed()
+                                    {
+                                        *mass = n64(value);
+                                    }
+                                }
+                            }
+                        });
+                        if ui
+                            .button("Subtract")
+                            .on_hover_text("Subtract the background from the selected spectrum")
+                            .clicked()
+                        {
+                            let result = match (
+                                self.parsed.get(&self.selected),
+                                self.parsed.get(&self.subtraction.background),
+                            ) {
+                                (Some(sample), Some(background)) => {
+                                    subtract(sample, background, self.subtraction.scale)
+                                }
+                                _ => Err(anyhow!("select a sample and a background")),
+                            };
+                            match result {
+                                Ok(parsed) => {
+                                    self.selected = self.derive(parsed);
+                                    self.subtraction.error = None;
+                                }
+                                Err(error) => {
+                                    error!(%error);
+                                    self.subtraction.error = Some(error);
+                                }
+                            }
+                        }
+                        if let Some(error) = &self.subtraction.error {
+                            ui.colored_label(ui.visuals().error_fg_color, format!("{error:#}"));
+                        }
+                    });
+                    // Consensus
+                    ui.collapsing(WidgetText::from("Consensus").heading(), |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Tolerance:");
+                            ui.add(
+                                DragValue::new(&mut self.consensus.tolerance)
+                                    .clamp_range(0.0..=f64::MAX)
+                                    .speed(0.01),
+                            )
+                            .on_hover_text("m/z");
+                        });
+                        if ui
+                            .button("Average")
+                            .on_hover_text("Average the included spectra")
+                            .clicked()
+                        {
+                            let (_, spectra) = self.included();
+                            match average(&spectra, self.consensus.tolerance) {
+                                Ok(parsed) => {
+                                    self.selected = self.derive(parsed);
+                                    self.consensus.error = None;
+                                }
+                                Err(error) => {
+                                    error!(%error);
+                                    self.consensus.error = Some(error);
+                                }
+                            }
+                        }
+                        if let Some(error) = &self.consensus.error {
+                            ui.colored_label(ui.visuals().error_fg_color, format!("{error:#}"));
+                        }
+                    });
+                    // Picolinyl
+                    ui.collapsing(WidgetText::from("Picolinyl").heading(), |ui| {
+                        ui.horizontal(|ui| {
+                            let given = &mut self.picolinyl.given;
+                            if ui
+                                .selectable_label(matches!(given, Given::Carbons(_)), "Carbons")
+                                .clicked()
+                            {
+                                *given = Given::Carbons(18);
+                            }
+                            if ui
+                                .selectable_label(
+                                    matches!(given, Given::MolecularIon(_)),
+                                    "Molecular ion",
+                                )
+                                .clicked()
+                            {
+                                *given = Given::MolecularIon(371);
+                            }
+                            match given {
+                                Given::Carbons(carbons) => {
+                                    ui.add(DragValue::new(carbons).clamp_range(4..=40).speed(1));
+                                }
+                                Given::MolecularIon(mass) => {
+                                    ui.add(DragValue::new(mass).speed(1)).on_hover_text("m/z");
+                                }
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            if ui
+                                .button("Interpret")
+                                .on_hover_text("Locate the double bonds of the selected spectrum")
+                                .clicked()
+                            {
+                                let result = match self.parsed.get(&self.selected) {
+                                    Some(parsed) => match self.picolinyl.given {
+                                        Given::Carbons(carbons) => {
+                                            Ok(Chain::from_carbons(&parsed.peaks, carbons))
+                                        }
+                                        Given::MolecularIon(mass) => {
+                                            Chain::from_molecular_ion(mass).ok_or_else(|| {
+                                                anyhow!(
+                                                    "{mass} isn't a picolinyl ester molecular ion"
+                                                )
+                                            })
+                                        }
+                                    }
+                                    .map(|chain| picolinyl(&parsed.peaks, chain)),
+                                    None => Err(anyhow!("select a spectrum")),
+                                };
+                                match result {
+                                    Ok(interpretation) => {
+                                        self.picolinyl.interpretation =
+                                            Some((self.selected, interpretation));
+                                        self.picolinyl.error = None;
+                                    }
+                                    Err(error) => {
+                                        error!(%error);
+                                        self.picolinyl.error = Some(error);
+                                    }
+                                }
+                            }
+                            if ui
+                                .add_enabled(
+                                    self.picolinyl.interpretation.is_some(),
+                                    Button::new("Clear"),
+                                )
+                                .clicked()
+                            {
+                                self.picolinyl.interpretation = None;
+                            }
+                        });
+                        if let Some((index, interpretation)) = &self.picolinyl.interpretation {
+                            if let Some(parsed) = self.parsed.get(index) {
+                                ui.label(format!("{}: {interpretation}", parsed.name));
+                            }
+                            for (position, (from, to)) in
+                                interpretation.positions.iter().zip(&interpretation.gaps)
+                            {
+                                ui.label(format!("Δ{position}: {from} → {to} (26)"));
+                            }
+                        }
+                        if let Some(error) = &self.picolinyl.error {
+                            ui.colored_label(ui.visuals().error_fg_color, format!("{error:#}"));
+                        }
+                    });
+                    // Theoretical
+                    ui.collapsing(WidgetText::from("Theoretical").heading(), |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Derivative:");
+                            ui.selectable_value_from_iter(
+                                &mut self.theoretical.derivative,
+                                [Derivative::Picolinyl, Derivative::Dmox, Derivative::Fame]
+                                    .into_iter(),
+                            );
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Carbons:");
+                            ui.add(
+                                DragValue::new(&mut self.theoretical.carbons)
+                                    .clamp_range(4..=40)
+                                    .speed(1),
+                            );
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Double bonds:");
+                            ui.add(
+                                TextEdit::singleline(&mut self.theoretical.double_bonds)
+                                    .hint_text("9,12")
+                                    .desired_width(64.0),
+                            )
+                            .on_hover_text("Δ positions, from the carboxyl");
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Branches:");
+                            ui.add(
+                                TextEdit::singleline(&mut self.theoretical.branches)
+                                    .hint_text("14")
+                                    .desired_width(64.0),
+                            )
+                            .on_hover_text("Methyl positions");
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Threshold:");
+                            ui.add(
+                                DragValue::new(&mut self.theoretical.threshold)
+                                    .clamp_range(0.0..=1.0)
+                                    .speed(0.001),
+                            )
+                            .on_hover_text("Minimal intensity relative to the base peak");
+                        });
+                        ui.horizontal(|ui| {
+                            if ui
+                                .button("Compare")
+                                .on_hover_text(
+                                    "Compare the expected ions with the selected spectrum",
+                                )
+                                .clicked()
+                            {
+                                let theoretical = &self.theoretical;
+                                let result = FattyAcid::new(
+                                    theoretical.carbons,
+                                    &theoretical.double_bonds,
+                                    &theoretical.branches,
+                                )
+                                .and_then(|fatty_acid| {
+                                    let parsed = self
+                                        .parsed
+                                        .get(&self.selected)
+                                        .ok_or_else(|| anyhow!("select a spectrum"))?;
+                                    let ions = fatty_acid.ions(theoretical.derivative);
+                                    let comparison =
+                                        compare(&ions, &parsed.peaks, theoretical.threshold);
+                                    Ok((fatty_acid, comparison))
+                                });
+                                match result {
+                                    Ok((fatty_acid, comparison)) => {
+                                        self.theoretical.comparison = Some((
+                                            self.selected,
+                                            fatty_acid,
+                                            self.theoretical.derivative,
+                                            comparison,
+                                        ));
+                                        self.theoretical.error = None;
+                                    }
+                                    Err(error) => {
+                                        error!(%error);
+                                        self.theoretical.error = Some(error);
+                                    }
+                                }
+                            }
+                            if ui
+                                .add_enabled(
+                                    self.theoretical.comparison.is_some(),
+                                    Button::new("Clear"),
+                                )
+                                .clicked()
+                            {
+                                self.theoretical.comparison = None;
+                            }
+                        });
+                        if let Some((index, fatty_acid, derivative, comparison)) =
+                            &self.theoretical.comparison
+                        {
+                            if let Some(parsed) = self.parsed.get(index) {
+                                ui.label(format!(
+                                    "{}: {fatty_acid} ({derivative}), {} of {} ions",
+                                    parsed.name,
+                                    comparison.matched.len(),
+                                    comparison.matched.len() + comparison.missing.len(),
+                                ));
+                            }
+                            let mut ions = comparison
+                                .matched
+                                .iter()
+                                .map(|ion| (ion, true))
+                                .chain(comparison.missing.iter().map(|ion| (ion, false)))
+                                .collect::<Vec<_>>();
+                            ions.sort_by_key(|(ion, _)| ion.mass);
+                            ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                                Grid::new("theoretical").striped(true).show(ui, |ui| {
+                                    for (ion, matched) in ions {
+                                        ui.label(ion.mass.to_string());
+                                        ui.label(ion.kind.to_string());
+                                        if matched {
+                                            ui.label("✔");
+                                        } else {
+                                            ui.colored_label(ui.visuals().error_fg_color, "✘");
+                                        }
+                                        ui.end_row();
+                                    }
+                                });
+                            });
+                        }
+                        if let Some(error) = &self.theoretical.error {
+                            ui.colored_label(ui.visuals().error_fg_color, format!("{error:#}"));
+                        }
+                    });
+                    // Similarity
+                    ui.collapsing(WidgetText::from("Similarity").heading(), |ui| {
+                        ui.horizontal_wrapped(|ui| {
+                            ui.label("Metric:");
+                            ui.selectable_value_from_iter(
+                                &mut self.similarity.metric,
+                                [
+                                    Metric::Cosine,
+                                    Metric::WeightedCosine,
+                                    Metric::Composite,
+                                    Metric::Entropy,
+                                    Metric::Pearson,
+                                ]
+                                .into_iter(),
+                            );
+                        });
+                        ui.add_enabled_ui(
+                            matches!(
+                                self.similarity.metric,
+                                Metric::WeightedCosine | Metric::Composite
+                            ),
+                            |ui| {
+                                ui.horizontal(|ui| {
+                                    ui.label("Weights:");
+                                    ui.add(
+                                        DragValue::new(&mut self.similarity.mass_exponent)
+                                            .speed(0.1),
+                                    )
+                                    .on_hover_text("m/z exponent");
+                                    ui.add(
+                                        DragValue::new(&mut self.similarity.intensity_exponent)
+                                            .speed(0.01),
+                                    )
+                                    .on_hover_text("Intensity exponent");
+                                });
+                            },
+                        );
+                    });
+                    // Export
+                    ui.collapsing(WidgetText::from("Export").heading(), |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Peaks per line:");
+                            ui.add(DragValue::new(&mut self.writer.peaks_per_line).speed(1))
+                                .on_hover_text("0 for all peaks in one line");
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Separator:");
+                            ui.selectable_value_from_iter(
+                                &mut self.writer.separator,
+                                [Separator::Semicolon, Separator::Colon, Separator::Tab]
+                                    .into_iter(),
+                            );
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Scaling:");
+                            let mut scaled = matches!(self.writer.scaling, Scaling::BasePeak(_));
+                            if ui.checkbox(&mut scaled, "").changed() {
+                                self.writer.scaling = if scaled {
+                                    Scaling::BasePeak(999.0)
+                                } else {
+                                    Scaling::None
+                                };
+                            }
+                            if let Scaling::BasePeak(base) = &mut self.writer.scaling {
+                                ui.add(DragValue::new(base).clamp_range(1.0..=f64::MAX).speed(1.0))
+                                    .on_hover_text("Base peak");
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Path:");
+                            ui.add(
+                                TextEdit::singleline(&mut self.export.path).hint_text("output.msp"),
+                            );
+                        });
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut self.export.overwrite, "Overwrite")
+                                .on_hover_text("Replace the file if it exists");
+                            if ui
+                                .add_enabled(
+                                    !cfg!(target_arch = "wasm32") && !self.export.path.is_empty(),
+                                    Button::new("Save"),
+                                )
+                                .on_hover_text("Save the included spectra")
+                                .on_disabled_hover_text("Choose a path (not on the web)")
+                                .clicked()
+                            {
+                                let library = self.parsed.iter().filter_map(|(index, parsed)| {
+                                    (!self.filter.contains(index)).then_some(parsed)
+                                });
+                                self.export.error = save(
+                                    Path::new(&self.export.path),
+                                    self.writer.write_library(library),
+                                    self.export.overwrite,
+                                )
+                                .err();
+                            }
+                        });
+                        if let Some(error) = &self.export.error {
+                            ui.colored_label(ui.visuals().error_fg_color, format!("{error:#}"));
+                        }
+                    });
+                    // Metadata
+                    ui.collapsing(WidgetText::from("Metadata").heading(), |ui| {
+                        let Some(parsed) = self.parsed.get(&self.selected) else {
+                            ui.label("No spectrum");
+                            return;
+                        };
+                        Grid::new("metadata").striped(true).show(ui, |ui| {
+                            ui.label("Name:");
+                            ui.label(&parsed.name);
+                            ui.end_row();
+                            if let Some(scan) = parsed.scan {
+                                ui.label("Scan:");
+                                ui.label(scan.to_string());
+                                ui.end_row();
+                            }
+                            if let Some(retention_time) = parsed.retention_time {
+                                ui.label("Retention time:");
+                                ui.label(format!("{:.3} min", retention_time.get::<minute>()));
+                                ui.end_row();
+                            }
+                            if let Some(model) = &parsed.model {
+                                ui.label("Model:");
+                                ui.label(model.to_string());
+                                ui.end_row();
+                            }
+                            if let Some(source) = &parsed.source {
+                                ui.label("Source:");
+                                ui.label(source);
+                                ui.end_row();
+                            }
+                            for (key, value) in parsed.metadata.iter() {
+                                ui.label(format!("{key}:"));
+                                ui.label(value);
+                                ui.end_row();
+                            }
+                        });
+                    });
                     // ui.collapsing(WidgetText::from("Visual").heading(), |ui| {
                     //     ui.separator();
                     //     ui.heading("Plot");
@@ -630,6 +1546,9 @@ impl App {
                 ui.separator();
                 ui.toggle_value(&mut self.left_panel, "🛠 Control");
                 ui.toggle_value(&mut self.errors.show, "⚠ Errors");
+                ui.toggle_value(&mut self.search.show, "🔍 Search");
+                ui.toggle_value(&mut self.heatmap.show, "▦ Similarity");
+                ui.toggle_value(&mut self.clustering.show, "🌳 Clustering");
                 if ui.button("Save Plot").clicked() {
                     self.save_plot = true;
                     // std::fs::write("output.svg", self.svg(ui).unwrap()).unwrap();
@@ -643,62 +1562,459 @@ impl App {
         Window::new("Errors")
             .open(&mut self.errors.show)
             .show(ctx, |ui| {
-                if self.errors.buffer.is_empty() {
+                let warnings = self
+                    .parsed
+                    .iter()
+                    .filter(|(_, parsed)| !parsed.warnings.is_empty());
+                if self.errors.buffer.is_empty() && warnings.clone().next().is_none() {
                     ui.label("No errors");
                 } else {
-                    self.errors.buffer.retain(|&index, error| {
-                        ui.horizontal(|ui| {
-                            ui.label(self.files[index].display().to_string())
-                                .on_hover_text(error.to_string());
-                            !ui.button("🗙").clicked()
-                        })
-                        .inner
+                    ScrollArea::vertical().show(ui, |ui| {
+                        // Warnings
+                        for (index, parsed) in warnings {
+                            let response = ui.label(RichText::new(&parsed.name).strong());
+                            if let Some(&source) = self.sources.get(index) {
+                                response.on_hover_text(self.files[source].display().to_string());
+                            }
+                            for warning in &parsed.warnings {
+                                ui.colored_label(
+                                    ui.visuals().warn_fg_color,
+                                    format!("⚠ {warning}"),
+                                );
+                            }
+                            ui.separator();
+                        }
+                        // Errors
+                        self.errors.buffer.retain(|&index, errors| {
+                            let keep = ui
+                                .horizontal(|ui| {
+                                    ui.label(
+                                        RichText::new(self.files[index].display().to_string())
+                                            .strong(),
+                                    );
+                                    !ui.button("🗙").clicked()
+                                })
+                                .inner;
+                            for error in errors.iter() {
+                                match error.downcast_ref::<parser::Error>() {
+                                    Some(error) => {
+                                        ui.label(error.to_string());
+                                        ui.label(snippet(ui, error));
+                                    }
+                                    None => {
+                                        ui.label(format!("{error:#}"));
+                                    }
+                                }
+                            }
+                            ui.separator();
+                            keep
+                        });
                     });
                 }
             });
     }
 
-    fn files(&mut self, ctx: &Context) {
-        // Show files (if any):
-        if !self.files.is_empty() {
-            let mut open = true;
-            Window::new("Files")
-                .anchor(Align2::RIGHT_BOTTOM, [0.0, 0.0])
-                .open(&mut open)
-                .show(ctx, |ui| {
-                    self.files.retain(with_index(|index, file: &DroppedFile| {
-                        ui.horizontal(|ui| {
-                            let mut include = !self.filter.contains(&index);
-                            if ui.checkbox(&mut include, "").changed() {
-                                if include {
-                                    self.filter.remove(&index);
-                                } else {
-                                    self.filter.insert(index);
-                                }
+    fn search(&mut self, ctx: &Context) {
+        let mut open = None;
+        Window::new("Library search")
+            .open(&mut self.search.show)
+            .show(ctx, |ui| {
+                // Libraries
+                ui.horizontal(|ui| {
+                    ui.label("Path:");
+                    ui.text_edit_singleline(&mut self.search.path);
+                    if ui.button("Load").clicked() {
+                        match Library::load(&self.search.path) {
+                            Ok(library) => {
+                                info!(path = %library.path.display(), spectra = library.spectra.len());
+                                self.search.libraries.push(library);
+                                self.search.error = None;
                             }
-                            ui.label(file.display().to_string());
-                            ui.color_edit_button_srgba(&mut self.colors[index]);
-                            !ui.button("🗙").clicked()
-                        })
-                        .inner
-                    }));
+                            Err(error) => {
+                                error!(%error);
+                                self.search.error = Some(error);
+                            }
+                        }
+                    }
                 });
-            if !open {
-                self.files.clear();
-            }
+                if let Some(error) = &self.search.error {
+                    ui.colored_label(ui.visuals().error_fg_color, format!("{error:#}"));
+                }
+                let mut removed = false;
+                self.search.libraries.retain(|library| {
+                    ui.horizontal(|ui| {
+                        ui.label(format!(
+                            "{} ({} spectra)",
+                            library.path.display(),
+                            library.spectra.len(),
+                        ));
+                        let keep = !ui.button("🗙").clicked();
+                        removed |= !keep;
+                        keep
+                    })
+                    .inner
+                });
+                if removed {
+                    self.search.hits.clear();
+                }
+                ui.separator();
+                // Search
+                ui.horizontal(|ui| {
+                    ui.label("Hits:");
+                    ui.add(
+                        DragValue::new(&mut self.search.count)
+                            .clamp_range(1..=usize::MAX)
+                            .speed(1),
+                    );
+                    let unknown = self.parsed.get(&self.selected);
+                    if ui
+                        .add_enabled(
+                            unknown.is_some() && !self.search.libraries.is_empty(),
+                            Button::new("Search"),
+                        )
+                        .on_hover_text("Search the selected spectrum")
+                        .clicked()
+                    {
+                        if let Some(unknown) = unknown {
+                            self.search.hits =
+                                search(unknown, &self.search.libraries, self.search.count);
+                        }
+                    }
+                });
+                if self.search.hits.is_empty() {
+                    return;
+                }
+                Grid::new("hits").striped(true).show(ui, |ui| {
+                    ui.label(RichText::new("#").strong());
+                    ui.label(RichText::new("Name").strong());
+                    ui.label(RichText::new("MF").strong())
+                        .on_hover_text("Match factor");
+                    ui.label(RichText::new("RMF").strong())
+                        .on_hover_text("Reverse match factor");
+                    ui.end_row();
+                    for (rank, &hit) in self.search.hits.iter().enumerate() {
+                        let library = &self.search.libraries[hit.library];
+                        ui.label((rank + 1).to_string());
+                        ui.label(&library.spectra[hit.spectrum].name)
+                            .on_hover_text(library.path.display().to_string());
+                        ui.label(format!("{:.0}", hit.match_factor));
+                        ui.label(format!("{:.0}", hit.reverse_match_factor));
+                        if ui.button("Mirror").clicked() {
+                            open = Some(hit);
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+        if let Some(hit) = open {
+            // The hit is compared with the selected spectrum, but excluded from
+            // the other arrangements and from saving. It replaces the previous
+            // one.
+            let parsed = self.search.libraries[hit.library].spectra[hit.spectrum].clone();
+            let index = match self.search.opened {
+                Some(index)
+                    if self.parsed.contains_key(&index) && !self.sources.contains_key(&index) =>
+                {
+                    self.parsed[&index] = parsed;
+                    index
+                }
+                _ => self.derive(parsed),
+            };
+            self.filter.insert(index);
+            self.search.opened = Some(index);
+            self.config.chart.arrangement = Arrangement::Mirror(index);
         }
     }
-}
 
-impl App {
-    fn rgb(&mut self, ui: &mut Ui) -> anyhow::Result<RetainedImage> {
-        let mut buf =
-            vec![0; RGBPixel::PIXEL_SIZE * (self.config.size.0 * self.config.size.1) as usize];
-        self.draw(
-            ui.ctx(),
-            BitMapBackend::with_buffer(&mut buf, self.config.size),
-        )?;
-        Ok(RetainedImage::from_color_image(
+    fn heatmap(&mut self, ctx: &Context) {
+        let mut open = self.heatmap.show;
+        Window::new("Similarity matrix")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Threshold:");
+                    ui.add(
+                        DragValue::new(&mut self.heatmap.threshold)
+                            .clamp_range(0.0..=1.0)
+                            .speed(0.01),
+                    )
+                    .on_hover_text("Least similarity within a cluster");
+                    if ui
+                        .button("Compute")
+                        .on_hover_text("Included spectra, ordered by cluster")
+                        .clicked()
+                    {
+                        let (indices, spectra) = self.included();
+                        let matrix = similarity::matrix(&self.similarity, &spectra);
+                        let clusters = similarity::clusters(&matrix, self.heatmap.threshold);
+                        let (order, matrix) = similarity::order(matrix, &clusters);
+                        self.heatmap.spectra = order.into_iter().map(|row| indices[row]).collect();
+                        self.heatmap.matrix = matrix;
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Path:");
+                    ui.add(
+                        TextEdit::singleline(&mut self.heatmap.export.path).hint_text("similarity"),
+                    )
+                    .on_hover_text("Without extension, saved as .svg and .csv");
+                    ui.checkbox(&mut self.heatmap.export.overwrite, "Overwrite")
+                        .on_hover_text("Replace the files if they exist");
+                    if ui
+                        .add_enabled(
+                            !cfg!(target_arch = "wasm32")
+                                && !self.heatmap.spectra.is_empty()
+                                && !self.heatmap.export.path.is_empty(),
+                            Button::new("Export"),
+                        )
+                        .on_hover_text("Heatmap (SVG) and matrix (CSV)")
+                        .on_disabled_hover_text("Compute and choose a path (not on the web)")
+                        .clicked()
+                    {
+                        self.heatmap.export.error = self.export_heatmap().err();
+                    }
+                });
+                if let Some(error) = &self.heatmap.export.error {
+                    ui.colored_label(ui.visuals().error_fg_color, format!("{error:#}"));
+                }
+                if self.heatmap.spectra.is_empty() {
+                    return;
+                }
+                let mut buf = vec![0; RGBPixel::PIXEL_SIZE * (HEATMAP.0 * HEATMAP.1) as usize];
+                match self.draw_heatmap(BitMapBackend::with_buffer(&mut buf, HEATMAP)) {
+                    Ok(()) => {
+                        let image = RetainedImage::from_color_image(
+                            "heatmap",
+                            ColorImage::from_rgb([HEATMAP.0 as _, HEATMAP.1 as _], &buf),
+                        );
+                        let width = ui.available_width().min(HEATMAP.0 as _);
+                        image.show_max_size(ui, Vec2::splat(width));
+                    }
+                    Err(error) => {
+                        ui.colored_label(ui.visuals().error_fg_color, format!("{error:#}"));
+                    }
+                }
+                ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                    Grid::new("heatmap").striped(true).show(ui, |ui| {
+                        for (number, index) in self.heatmap.spectra.iter().enumerate() {
+                            ui.label((number + 1).to_string());
+                            ui.label(self.parsed.get(index).map_or("", |parsed| &parsed.name));
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+        self.heatmap.show = open;
+    }
+
+    fn clustering(&mut self, ctx: &Context) {
+        let mut open = self.clustering.show;
+        Window::new("Clustering").open(&mut open).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Linkage:");
+                ui.selectable_value_from_iter(
+                    &mut self.clustering.linkage,
+                    [Linkage::Average, Linkage::Complete, Linkage::Single].into_iter(),
+                );
+            });
+            ui.horizontal(|ui| {
+                if ui
+                    .button("Compute")
+                    .on_hover_text("Included spectra, distance 1 - similarity")
+                    .clicked()
+                {
+                    let (indices, spectra) = self.included();
+                    let distances = similarity::matrix(&self.similarity, &spectra)
+                        .mapv(|similarity| (1.0 - similarity).max(0.0));
+                    self.clustering.dendrogram = cluster(&distances, self.clustering.linkage);
+                    self.clustering.spectra = indices;
+                }
+                if ui
+                    .add_enabled(
+                        !self.clustering.spectra.is_empty(),
+                        Button::new("Reorder files"),
+                    )
+                    .on_hover_text("Order the Files window as the leaves")
+                    .clicked()
+                {
+                    let leaves = self
+                        .clustering
+                        .dendrogram
+                        .order()
+                        .into_iter()
+                        .map(|leaf| self.clustering.spectra[leaf])
+                        .collect::<Vec<_>>();
+                    order::sort(&mut self.parsed, &leaves);
+                }
+            });
+            if self.clustering.spectra.is_empty() {
+                return;
+            }
+            let size = HEATMAP;
+            let mut buf = vec![0; RGBPixel::PIXEL_SIZE * (size.0 * size.1) as usize];
+            match self.draw_dendrogram(BitMapBackend::with_buffer(&mut buf, size)) {
+                Ok(()) => {
+                    let image = RetainedImage::from_color_image(
+                        "dendrogram",
+                        ColorImage::from_rgb([size.0 as _, size.1 as _], &buf),
+                    );
+                    let width = ui.available_width().min(size.0 as _);
+                    image.show_max_size(ui, Vec2::splat(width));
+                }
+                Err(error) => {
+                    ui.colored_label(ui.visuals().error_fg_color, format!("{error:#}"));
+                }
+            }
+            ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                Grid::new("dendrogram").striped(true).show(ui, |ui| {
+                    for (number, leaf) in self.clustering.dendrogram.order().into_iter().enumerate()
+                    {
+                        let index = self.clustering.spectra[leaf];
+                        ui.label((number + 1).to_string());
+                        ui.label(self.parsed.get(&index).map_or("", |parsed| &parsed.name));
+                        ui.end_row();
+                    }
+                });
+            });
+        });
+        self.clustering.show = open;
+    }
+
+    fn files(&mut self, ctx: &Context) {
+        // Show spectra (if any):
+        if !self.parsed.is_empty() {
+            let mut open = true;
+            Window::new("Files")
+                .anchor(Align2::RIGHT_BOTTOM, [0.0, 0.0])
+                .open(&mut open)
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        if ui
+                            .button("Sort by retention time")
+                            .on_hover_text("Spectra without retention time go first")
+                            .clicked()
+                        {
+                            order::by_retention_time(&mut self.parsed);
+                        }
+                        if ui
+                            .button("Sort by molecular ion")
+                            .on_hover_text(
+                                "Molecular ions of the filenames, spectra without one go first",
+                            )
+                            .clicked()
+                        {
+                            order::by_molecular_ion(&mut self.parsed);
+                        }
+                    });
+                    ScrollArea::vertical().show(ui, |ui| {
+                        let mut reload = None;
+                        // Spectra of every file, in the order of the spectra, then the
+                        // derived ones
+                        let groups =
+                            order::groups(self.parsed.keys(), &self.sources, self.files.len());
+                        for group in groups {
+                            let Some(file) = group else {
+                                ui.label(RichText::new("Derived").strong());
+                                self.spectra(ui, group);
+                                continue;
+                            };
+                            ui.horizontal(|ui| {
+                                ui.label(
+                                    RichText::new(self.files[file].display().to_string()).strong(),
+                                );
+                                let detected = self
+                                    .decoded
+                                    .get(&file)
+                                    .map_or("", |encoding| encoding.name());
+                                let mut encoding = self.encodings.get(&file).copied();
+                                ComboBox::from_id_source(("encoding", file))
+                                    .selected_text(match encoding {
+                                        Some(encoding) => encoding.name().to_owned(),
+                                        None => format!("Auto ({detected})"),
+                                    })
+                                    .show_ui(ui, |ui| {
+                                        ui.selectable_value(
+                                            &mut encoding,
+                                            None,
+                                            format!("Auto ({detected})"),
+                                        );
+                                        for value in ENCODINGS {
+                                            ui.selectable_value(
+                                                &mut encoding,
+                                                Some(value),
+                                                value.name(),
+                                            );
+                                        }
+                                    });
+                                if encoding != self.encodings.get(&file).copied() {
+                                    match encoding {
+                                        Some(encoding) => self.encodings.insert(file, encoding),
+                                        None => self.encodings.remove(&file),
+                                    };
+                                    reload = Some(file);
+                                }
+                            });
+                            self.spectra(ui, group);
+                            ui.separator();
+                        }
+                        if let Some(file) = reload {
+                            self.load(file);
+                        }
+                    });
+                });
+            if !open {
+                self.clear();
+            }
+        }
+    }
+}
+
+impl App {
+    /// Rows of the Files window for the spectra of the file (`None` for the
+    /// derived spectra)
+    fn spectra(&mut self, ui: &mut Ui, file: Option<usize>) {
+        let mut removed = None;
+        for (&index, parsed) in &self.parsed {
+            if self.sources.get(&index).copied() != file {
+                continue;
+            }
+            ui.horizontal(|ui| {
+                let mut include = !self.filter.contains(&index);
+                if ui.checkbox(&mut include, "").changed() {
+                    if include {
+                        self.filter.remove(&index);
+                    } else {
+                        self.filter.insert(index);
+                    }
+                }
+                ui.selectable_value(&mut self.selected, index, &parsed.name);
+                ui.color_edit_button_srgba(&mut self.colors[&index]);
+                if ui.button("🗙").clicked() {
+                    removed = Some(index);
+                }
+            });
+        }
+        if let Some(index) = removed {
+            self.remove(index);
+        }
+    }
+
+    /// Adds a spectrum without a source file
+    fn derive(&mut self, parsed: Parsed) -> usize {
+        let index = self.parsed.keys().max().map_or(0, |max| max + 1);
+        self.parsed.insert(index, parsed);
+        self.colors.insert(index, color(index));
+        index
+    }
+
+    fn rgb(&mut self, ui: &mut Ui) -> anyhow::Result<RetainedImage> {
+        let mut buf =
+            vec![0; RGBPixel::PIXEL_SIZE * (self.config.size.0 * self.config.size.1) as usize];
+        self.draw(
+            ui.ctx(),
+            BitMapBackend::with_buffer(&mut buf, self.config.size),
+        )?;
+        Ok(RetainedImage::from_color_image(
             "rgb",
             ColorImage::from_rgb([self.config.size.0 as _, self.config.size.1 as _], &buf),
         ))
@@ -720,36 +2036,28 @@ impl App {
     {
         let drawing_area = drawing_backend.into_drawing_area();
         drawing_area.fill(&WHITE)?;
-        // Labels
-        for label in &*self.labels {
+        // Labels (pixel anchored)
+        for label in self
+            .labels
+            .iter()
+            .filter(|label| label.coordinates.anchor == Anchor::Pixel)
+        {
             drawing_area.draw_text(
                 &label.text,
-                &self
-                    .config
-                    .chart
-                    .labels
-                    .font
-                    .style()
-                    .into_text_style(&self.config.chart.size)
-                    .color(&BLACK),
+                &self.label_style(),
                 (label.coordinates.x as _, label.coordinates.y as _),
             )?;
         }
-        // Points
-        for point in &*self.points {
+        // Points (pixel anchored)
+        for point in self
+            .points
+            .iter()
+            .filter(|point| point.coordinates.anchor == Anchor::Pixel)
+        {
             drawing_area.draw(&Circle::new(
                 (point.coordinates.x as _, point.coordinates.y as _),
                 point.size,
-                ShapeStyle {
-                    color: RGBAColor(
-                        point.color.r(),
-                        point.color.g(),
-                        point.color.b(),
-                        point.color.a() as f64 / u8::MAX as f64,
-                    ),
-                    filled: point.filled,
-                    stroke_width: default(),
-                },
+                point.style(),
             ))?;
             // drawing_area.draw(&Circle::new(
             //     (point.coordinates.x, point.coordinates.y),
@@ -757,57 +2065,896 @@ impl App {
             //     original_style,
             // ));
         }
+        // Spectra
+        let spectra = match self.config.chart.arrangement {
+            Arrangement::Single => self
+                .parsed
+                .get(&self.selected)
+                .map(|parsed| (self.selected, parsed, BLACK))
+                .into_iter()
+                .collect(),
+            Arrangement::Mirror(other) => [self.selected, other]
+                .into_iter()
+                .filter_map(|index| {
+                    Some((
+                        index,
+                        self.parsed.get(&index)?,
+                        rgb_color(self.colors[&index]),
+                    ))
+                })
+                .collect(),
+            _ => self
+                .parsed
+                .iter()
+                .filter(|(index, _)| !self.filter.contains(index))
+                .map(|(&index, parsed)| (index, parsed, rgb_color(self.colors[&index])))
+                .collect::<Vec<_>>(),
+        };
+        if spectra.is_empty() {
+            return Ok(());
+        }
+        match self.config.chart.arrangement {
+            Arrangement::Single => {
+                let (index, parsed, color) = spectra[0];
+                self.spectrum(
+                    context,
+                    &drawing_area,
+                    index,
+                    parsed,
+                    color,
+                    &self.config.chart.caption.text,
+                )?;
+            }
+            Arrangement::Overlay => self.overlay(context, &drawing_area, &spectra, (0.0, 0.0))?,
+            Arrangement::Stack => {
+                let areas = drawing_area.split_evenly((spectra.len(), 1));
+                for (area, &(index, parsed, color)) in areas.iter().zip(&spectra) {
+                    self.spectrum(context, area, index, parsed, color, &caption(parsed))?;
+                }
+            }
+            Arrangement::Waterfall { x, y } => {
+                self.overlay(context, &drawing_area, &spectra, (x, y))?
+            }
+            Arrangement::Mirror(_) => {
+                if let [top, bottom] = spectra[..] {
+                    self.mirror(context, &drawing_area, top, bottom)?;
+                }
+            }
+        }
+        // To avoid the IO failure being ignored silently, we manually call the present function
+        drawing_area.present().expect("Unable to write result to file, please make sure 'plotters-doc-data' dir exists under current dir");
+        Ok(())
+    }
+
+    /// Draws the spectrum (of the index) in its own chart
+    fn spectrum<T>(
+        &self,
+        context: &Context,
+        drawing_area: &DrawingArea<T, Shift>,
+        index: usize,
+        parsed: &Parsed,
+        color: RGBColor,
+        caption: &str,
+    ) -> Result<()>
+    where
+        T: DrawingBackend,
+        <T as DrawingBackend>::ErrorType: 'static,
+    {
+        let peaks = self.peaks(context, parsed);
+        let style = self.annotation_style(drawing_area);
+        if parsed.is_nominal() {
+            let mut chart = self.builder(drawing_area, caption).build_cartesian_2d(
+                self.config.bounds.x.nominal().into_segmented(),
+                self.config.bounds.y.range(),
+            )?;
+            self.mesh(&mut chart)?;
+            chart.draw_series(
+                Histogram::vertical(&chart)
+                    .style(color.filled())
+                    .margin(self.margin1)
+                    .data(
+                        peaks
+                            .iter()
+                            .map(|(mass, &intensity)| (mass.raw() as u64, intensity)),
+                    ),
+            )?;
+            chart.draw_series(self.deviations(parsed, &peaks).map(
+                |(mass, intensity, deviation)| {
+                    ErrorBar::new_vertical(
+                        SegmentValue::CenterOf(mass as u64),
+                        intensity - deviation,
+                        intensity,
+                        intensity + deviation,
+                        BLACK.stroke_width(self.config.chart.axes.stroke_width),
+                        ERROR_BAR_WIDTH,
+                    )
+                },
+            ))?;
+            let labels = self
+                .annotations(parsed, &peaks)
+                .map(|(mass, intensity, annotation)| {
+                    ((SegmentValue::CenterOf(mass as u64), intensity), annotation)
+                })
+                .collect();
+            self.annotate(&mut chart, labels, &style, false)?;
+            let peaks = peaks
+                .iter()
+                .map(|(mass, &intensity)| (mass.raw(), intensity))
+                .collect::<Vec<_>>();
+            let coordinate = |x: f64, y| (SegmentValue::CenterOf(x.round() as u64), y);
+            self.marks(&mut chart, &peaks, coordinate)?;
+            self.draw_diagnostic_ions(&mut chart, index, &peaks, coordinate)?;
+            self.draw_comparison(&mut chart, index, &peaks, coordinate)?;
+            self.draw_differences(
+                &mut chart,
+                &self.differences(index),
+                &peaks,
+                &style,
+                coordinate,
+            )?;
+        } else {
+            // Fractional masses can't be segmented, so peaks are sticks
+            let mut chart = self
+                .builder(drawing_area, caption)
+                .build_cartesian_2d(self.config.bounds.x.range(), self.config.bounds.y.range())?;
+            self.mesh(&mut chart)?;
+            chart.draw_series(peaks.iter().map(|(mass, &intensity)| {
+                PathElement::new(
+                    [(mass.raw(), 0.0), (mass.raw(), intensity)],
+                    color.stroke_width(self.config.chart.axes.stroke_width),
+                )
+            }))?;
+            chart.draw_series(self.deviations(parsed, &peaks).map(
+                |(mass, intensity, deviation)| {
+                    ErrorBar::new_vertical(
+                        mass,
+                        intensity - deviation,
+                        intensity,
+                        intensity + deviation,
+                        BLACK.stroke_width(self.config.chart.axes.stroke_width),
+                        ERROR_BAR_WIDTH,
+                    )
+                },
+            ))?;
+            let labels = self
+                .annotations(parsed, &peaks)
+                .map(|(mass, intensity, annotation)| ((mass, intensity), annotation))
+                .collect();
+            self.annotate(&mut chart, labels, &style, false)?;
+            let peaks = peaks
+                .iter()
+                .map(|(mass, &intensity)| (mass.raw(), intensity))
+                .collect::<Vec<_>>();
+            self.marks(&mut chart, &peaks, |x, y| (x, y))?;
+            self.draw_diagnostic_ions(&mut chart, index, &peaks, |x, y| (x, y))?;
+            self.draw_comparison(&mut chart, index, &peaks, |x, y| (x, y))?;
+            self.draw_differences(
+                &mut chart,
+                &self.differences(index),
+                &peaks,
+                &style,
+                |x, y| (x, y),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Draws the spectra as sticks in one chart, each offset by the step from
+    /// the previous one
+    fn overlay<T>(
+        &self,
+        context: &Context,
+        drawing_area: &DrawingArea<T, Shift>,
+        spectra: &[(usize, &Parsed, RGBColor)],
+        step: (f64, f64),
+    ) -> Result<()>
+    where
+        T: DrawingBackend,
+        <T as DrawingBackend>::ErrorType: 'static,
+    {
+        let bounds = &self.config.bounds;
+        let steps = spectra.len().saturating_sub(1) as f64;
+        let mut chart = self
+            .builder(drawing_area, &self.config.chart.caption.text)
+            .build_cartesian_2d(
+                bounds.x.start..bounds.x.end + steps * step.0,
+                bounds.y.start..bounds.y.end + steps * step.1,
+            )?;
+        self.mesh(&mut chart)?;
+        let style = self.annotation_style(drawing_area);
+        let stroke_width = self.config.chart.axes.stroke_width;
+        let mut points = Vec::new();
+        for (index, &(_, parsed, color)) in spectra.iter().enumerate() {
+            let (x, y) = (index as f64 * step.0, index as f64 * step.1);
+            let peaks = self.peaks(context, parsed);
+            points.extend(
+                peaks
+                    .iter()
+                    .map(|(mass, &intensity)| (mass.raw() + x, intensity + y)),
+            );
+            // Baseline
+            if y != 0.0 {
+                chart.draw_series([PathElement::new(
+                    [(bounds.x.start + x, y), (bounds.x.end + x, y)],
+                    color.stroke_width(stroke_width),
+                )])?;
+            }
+            chart
+                .draw_series(peaks.iter().map(|(mass, &intensity)| {
+                    PathElement::new(
+                        [(mass.raw() + x, y), (mass.raw() + x, intensity + y)],
+                        color.stroke_width(stroke_width),
+                    )
+                }))?
+                .label(&parsed.name)
+                .legend(move |(x, y)| {
+                    PathElement::new([(x, y), (x + 20, y)], color.stroke_width(stroke_width))
+                });
+            let labels = self
+                .annotations(parsed, &peaks)
+                .map(|(mass, intensity, annotation)| ((mass + x, intensity + y), annotation))
+                .collect();
+            self.annotate(&mut chart, labels, &style, false)?;
+        }
+        self.marks(&mut chart, &points, |x, y| (x, y))?;
+        self.draw_differences(&mut chart, &self.differences, &points, &style, |x, y| {
+            (x, y)
+        })?;
+        chart
+            .configure_series_labels()
+            .label_font(self.config.chart.axes.labels.font.style())
+            .background_style(WHITE)
+            .border_style(BLACK)
+            .draw()?;
+        Ok(())
+    }
+
+    /// Draws the first spectrum up and the second one inverted, with their
+    /// similarity in the corner
+    fn mirror<T>(
+        &self,
+        context: &Context,
+        drawing_area: &DrawingArea<T, Shift>,
+        (_, top, top_color): (usize, &Parsed, RGBColor),
+        (_, bottom, bottom_color): (usize, &Parsed, RGBColor),
+    ) -> Result<()>
+    where
+        T: DrawingBackend,
+        <T as DrawingBackend>::ErrorType: 'static,
+    {
+        let bounds = &self.config.bounds;
+        let x = bounds.x.range();
+        let mut chart = self
+            .builder(drawing_area, &self.config.chart.caption.text)
+            .build_cartesian_2d(x.clone(), -bounds.y.end..bounds.y.end)?;
+        self.mesh(&mut chart)?;
+        let stroke_width = self.config.chart.axes.stroke_width;
+        // Zero line
+        chart.draw_series([PathElement::new(
+            [(x.start, 0.0), (x.end, 0.0)],
+            BLACK.stroke_width(stroke_width),
+        )])?;
+        let mut points = Vec::new();
+        let mut normalized = Vec::with_capacity(2);
+        for (parsed, color, sign) in [(top, top_color, 1.0), (bottom, bottom_color, -1.0)] {
+            let peaks = self.peaks(context, parsed);
+            points.extend(
+                peaks
+                    .iter()
+                    .map(|(mass, &intensity)| (mass.raw(), sign * intensity)),
+            );
+            chart
+                .draw_series(peaks.iter().map(|(mass, &intensity)| {
+                    PathElement::new(
+                        [(mass.raw(), 0.0), (mass.raw(), sign * intensity)],
+                        color.stroke_width(stroke_width),
+                    )
+                }))?
+                .label(&parsed.name)
+                .legend(move |(x, y)| {
+                    PathElement::new([(x, y), (x + 20, y)], color.stroke_width(stroke_width))
+                });
+            let labels = self
+                .annotations(parsed, &peaks)
+                .map(|(mass, intensity, annotation)| ((mass, sign * intensity), annotation))
+                .collect();
+            let style = self.annotation_style(drawing_area);
+            self.annotate(&mut chart, labels, &style, sign < 0.0)?;
+            normalized.push(peaks);
+        }
+        self.marks(&mut chart, &points, |x, y| (x, y))?;
+        let style = self.annotation_style(drawing_area);
+        self.draw_differences(&mut chart, &self.differences, &points, &style, |x, y| {
+            (x, y)
+        })?;
+        // Similarity of the peaks drawn
+        chart.draw_series([plotters::element::Text::new(
+            format!(
+                "{}: {:.3}",
+                self.similarity.metric,
+                self.similarity.normalized(&normalized[0], &normalized[1]),
+            ),
+            (x.end, bounds.y.end),
+            self.annotation_style(drawing_area)
+                .pos(Pos::new(HPos::Right, VPos::Top)),
+        )])?;
+        chart
+            .configure_series_labels()
+            .position(SeriesLabelPosition::LowerRight)
+            .label_font(self.config.chart.axes.labels.font.style())
+            .background_style(WHITE)
+            .border_style(BLACK)
+            .draw()?;
+        Ok(())
+    }
+
+    /// Draws the similarity matrix as a heatmap labeled with the spectrum
+    /// names, the first spectrum on top
+    fn draw_heatmap<T>(&self, drawing_backend: T) -> Result<()>
+    where
+        T: DrawingBackend,
+        <T as DrawingBackend>::ErrorType: 'static,
+    {
+        let drawing_area = drawing_backend.into_drawing_area();
+        drawing_area.fill(&WHITE)?;
+        let count = self.heatmap.spectra.len();
         let mut chart = ChartBuilder::on(&drawing_area)
+            .margin(self.config.chart.margin)
             .x_label_area_size(self.config.chart.axes.labels.x.area_size)
             .y_label_area_size(self.config.chart.axes.labels.y.area_size)
-            .margin(self.config.chart.margin)
-            .caption(
-                &self.config.chart.caption,
-                self.config.chart.caption.font.style(),
+            .build_cartesian_2d((0..count).into_segmented(), (0..count).into_segmented())?;
+        let name = |row: usize| {
+            let name = self
+                .parsed
+                .get(&self.heatmap.spectra[row])
+                .map_or("", |parsed| &parsed.name);
+            match name.char_indices().nth(HEATMAP_LABEL) {
+                Some((end, _)) => format!("{}…", &name[..end]),
+                None => name.to_owned(),
+            }
+        };
+        chart
+            .configure_mesh()
+            .disable_mesh()
+            .x_labels(count)
+            .y_labels(count)
+            .x_label_formatter(&|value| match *value {
+                SegmentValue::CenterOf(value) if value < count => name(value),
+                _ => String::new(),
+            })
+            .y_label_formatter(&|value| match *value {
+                SegmentValue::CenterOf(value) if value < count => name(count - 1 - value),
+                _ => String::new(),
+            })
+            .label_style(self.config.chart.axes.labels.font.style())
+            .axis_style(BLACK.stroke_width(self.config.chart.axes.stroke_width))
+            .draw()?;
+        let cells = self
+            .heatmap
+            .matrix
+            .indexed_iter()
+            .map(|((row, column), &value)| (column, count - 1 - row, value));
+        chart.draw_series(cells.clone().map(|(x, y, value)| {
+            // White to red
+            let shade = (255.0 * (1.0 - value.clamp(0.0, 1.0))) as u8;
+            Rectangle::new(
+                [
+                    (SegmentValue::Exact(x), SegmentValue::Exact(y)),
+                    (SegmentValue::Exact(x + 1), SegmentValue::Exact(y + 1)),
+                ],
+                RGBColor(255, shade, shade).filled(),
             )
+        }))?;
+        let style = self
+            .config
+            .chart
+            .axes
+            .labels
+            .font
+            .style()
+            .into_text_style(&drawing_area)
+            .pos(Pos::new(HPos::Center, VPos::Center));
+        let (light, dark) = (style.color(&WHITE), style.color(&BLACK));
+        chart.draw_series(cells.map(|(x, y, value)| {
+            plotters::element::Text::new(
+                format!("{value:.2}"),
+                (SegmentValue::CenterOf(x), SegmentValue::CenterOf(y)),
+                if value > 0.6 { &light } else { &dark }.clone(),
+            )
+        }))?;
+        drawing_area.present()?;
+        Ok(())
+    }
+
+    /// Saves the heatmap and the matrix next to each other
+    fn export_heatmap(&self) -> Result<()> {
+        let path = Path::new(&self.heatmap.export.path);
+        let mut svg = String::new();
+        self.draw_heatmap(SVGBackend::with_string(&mut svg, HEATMAP))?;
+        let mut csv = String::new();
+        let names = self
+            .heatmap
+            .spectra
+            .iter()
+            .map(|index| {
+                let name = self.parsed.get(index).map_or("", |parsed| &parsed.name);
+                format!("\"{}\"", name.replace('"', "\"\""))
+            })
+            .collect::<Vec<_>>();
+        writeln!(csv, ",{}", names.join(","))?;
+        for (name, row) in names.iter().zip(self.heatmap.matrix.rows()) {
+            writeln!(csv, "{name},{}", row.iter().join(","))?;
+        }
+        let (svg_path, csv_path) = (path.with_extension("svg"), path.with_extension("csv"));
+        let overwrite = self.heatmap.export.overwrite;
+        // Neither file is written if the other one can't be
+        if !overwrite && csv_path.exists() {
+            bail!("{} already exists", csv_path.display());
+        }
+        save(&svg_path, svg, overwrite)?;
+        save(&csv_path, csv, true)
+    }
+
+    /// Draws the dendrogram, leaves numbered from left to right
+    fn draw_dendrogram<T>(&self, drawing_backend: T) -> Result<()>
+    where
+        T: DrawingBackend,
+        <T as DrawingBackend>::ErrorType: 'static,
+    {
+        let drawing_area = drawing_backend.into_drawing_area();
+        drawing_area.fill(&WHITE)?;
+        let dendrogram = &self.clustering.dendrogram;
+        let order = dendrogram.order();
+        // Position and height of every cluster
+        let mut positions = vec![0.0; dendrogram.leaves + dendrogram.merges.len()];
+        let mut heights = vec![0.0; positions.len()];
+        for (position, &leaf) in order.iter().enumerate() {
+            positions[leaf] = (position + 1) as f64;
+        }
+        for (index, merge) in dendrogram.merges.iter().enumerate() {
+            positions[dendrogram.leaves + index] =
+                (positions[merge.left] + positions[merge.right]) / 2.0;
+            heights[dendrogram.leaves + index] = merge.distance;
+        }
+        let height = heights.iter().copied().fold(f64::EPSILON, f64::max);
+        let mut chart = ChartBuilder::on(&drawing_area)
+            .margin(self.config.chart.margin)
+            .y_label_area_size(self.config.chart.axes.labels.y.area_size)
             .build_cartesian_2d(
-                self.config.bounds.x.range().into_segmented(),
-                self.config.bounds.y.start as _..self.config.bounds.y.end as f64,
+                0.0..(dendrogram.leaves + 1) as f64,
+                -0.1 * height..1.05 * height,
             )?;
         chart
             .configure_mesh()
             .disable_mesh()
-            .x_desc(&self.config.chart.axes.descriptions.x)
-            .y_desc(&self.config.chart.axes.descriptions.y)
+            .disable_x_axis()
+            .y_desc("Distance")
             .axis_desc_style(self.config.chart.axes.descriptions.font.style())
             .label_style(self.config.chart.axes.labels.font.style())
-            .x_labels(100)
-            .set_all_tick_mark_size(RelativeSize::Width(self.tick_mark_size))
             .axis_style(BLACK.stroke_width(self.config.chart.axes.stroke_width))
             .draw()?;
-        // chart.configure_series_labels().draw()?;
+        let stroke_width = self.config.chart.axes.stroke_width;
+        chart.draw_series(dendrogram.merges.iter().map(|merge| {
+            let (left, right) = (merge.left, merge.right);
+            PathElement::new(
+                [
+                    (positions[left], heights[left]),
+                    (positions[left], merge.distance),
+                    (positions[right], merge.distance),
+                    (positions[right], heights[right]),
+                ],
+                BLACK.stroke_width(stroke_width),
+            )
+        }))?;
+        let style = self
+            .config
+            .chart
+            .axes
+            .labels
+            .font
+            .style()
+            .into_text_style(&drawing_area)
+            .pos(Pos::new(HPos::Center, VPos::Top));
+        chart.draw_series((0..order.len()).map(|position| {
+            plotters::element::Text::new(
+                (position + 1).to_string(),
+                ((position + 1) as f64, 0.0),
+                style.clone(),
+            )
+        }))?;
+        drawing_area.present()?;
+        Ok(())
+    }
+
+    /// Included (not filtered) spectra and their indices
+    fn included(&self) -> (Vec<usize>, Vec<&Parsed>) {
+        self.parsed
+            .iter()
+            .filter(|(index, _)| !self.filter.contains(index))
+            .map(|(&index, parsed)| (index, parsed))
+            .unzip()
+    }
+
+    /// Mass bound end fitting the molecular ions of the included spectra (or
+    /// their largest masses), rounded up to ten
+    fn fit(&self) -> Option<u64> {
+        let (_, included) = self.included();
+        let mass = included
+            .into_iter()
+            .filter_map(|parsed| {
+                molecular_ion(parsed).or_else(|| {
+                    let (mass, _) = parsed.peaks.last_key_value()?;
+                    Some(mass.raw().ceil() as u64)
+                })
+            })
+            .max()?;
+        Some((mass / 10 + 1) * 10)
+    }
 
-        // Parsed
-        let parsed = &self.parsed[&0];
-        // Bounded
+    /// Bounded and normalized peaks
+    fn peaks(&self, context: &Context, parsed: &Parsed) -> BTreeMap<N64, f64> {
         let peaks = context.memory_mut(|memory| {
             memory
                 .caches
                 .cache::<Bounded>()
                 .get((&parsed.peaks, &self.config.bounds))
         });
-        // Normalized
-        let peaks =
-            context.memory_mut(|memory| memory.caches.cache::<Normalized>().get((&peaks, true)));
+        context.memory_mut(|memory| memory.caches.cache::<Normalized>().get((&peaks, true)))
+    }
+
+    /// Annotations of the peaks (if enabled), or m/z labels of the picked
+    /// peaks (if enabled)
+    fn annotations<'a>(
+        &'a self,
+        parsed: &'a Parsed,
+        peaks: &'a BTreeMap<N64, f64>,
+    ) -> impl Iterator<Item = (f64, f64, String)> + 'a {
+        let picked = if self.config.chart.peak_labels {
+            self.config.chart.picker.pick(peaks)
+        } else {
+            Vec::new()
+        };
+        peaks.iter().filter_map(move |(mass, &intensity)| {
+            let annotation = parsed
+                .annotations
+                .get(mass)
+                .filter(|_| self.config.chart.annotations)
+                .cloned()
+                .or_else(|| picked.binary_search(mass).is_ok().then(|| mass.to_string()))?;
+            Some((mass.raw(), intensity, annotation))
+        })
+    }
+
+    /// Standard deviations of the peaks (consensus spectra), normalized as the
+    /// peaks
+    fn deviations<'a>(
+        &self,
+        parsed: &'a Parsed,
+        peaks: &'a BTreeMap<N64, f64>,
+    ) -> impl Iterator<Item = (f64, f64, f64)> + 'a {
+        peaks.iter().filter_map(|(mass, &intensity)| {
+            let deviation = parsed.deviations.get(mass)?;
+            let scale = intensity / parsed.peaks.get(mass)?.raw();
+            Some((mass.raw(), intensity, deviation.raw() * scale))
+        })
+    }
+
+    /// Draws the labels above (or below) their anchors, laid out apart from
+    /// each other and from the axes, with leader lines to the displaced ones
+    fn annotate<T, X, Y>(
+        &self,
+        chart: &mut ChartContext<T, Cartesian2d<X, Y>>,
+        labels: Vec<Anchored<X::ValueType, Y::ValueType>>,
+        style: &plotters::style::TextStyle,
+        below: bool,
+    ) -> Result<()>
+    where
+        T: DrawingBackend,
+        <T as DrawingBackend>::ErrorType: 'static,
+        X: Ranged,
+        Y: Ranged,
+        X::ValueType: Clone,
+        Y::ValueType: Clone,
+    {
+        let style = style.pos(Pos::new(
+            HPos::Center,
+            if below { VPos::Top } else { VPos::Bottom },
+        ));
+        let mut boxes = Vec::with_capacity(labels.len());
+        for (anchor, text) in &labels {
+            boxes.push((
+                chart.backend_coord(anchor),
+                chart.plotting_area().estimate_text_size(text, &style)?,
+            ));
+        }
+        let offsets = layout(
+            &boxes,
+            &chart.plotting_area().get_pixel_range(),
+            LABEL_GAP,
+            below,
+        );
+        let natural = (0, if below { LABEL_GAP } else { -LABEL_GAP });
         chart.draw_series(
-            Histogram::vertical(&chart)
-                .style(BLACK.filled())
-                .margin(self.margin1)
-                // .data(
-                //     data.iter()
-                //         .enumerate()
-                //         .map(|(index, &x)| (index as u64, x as f64)),
-                // ),
-                // .data(data.iter().map(|(&x, &y)| (x, y))),
-                .data(peaks),
+            labels
+                .iter()
+                .zip(&offsets)
+                .filter(|(_, &offset)| offset != natural)
+                .map(|((anchor, _), &offset)| {
+                    EmptyElement::at(anchor.clone())
+                        + PathElement::new([(0, 0), offset], BLACK.stroke_width(1))
+                }),
+        )?;
+        chart.draw_series(
+            labels
+                .into_iter()
+                .zip(offsets)
+                .map(|((anchor, text), offset)| {
+                    EmptyElement::at(anchor)
+                        + plotters::element::Text::new(text, offset, style.clone())
+                }),
+        )?;
+        Ok(())
+    }
+
+    /// Draws the labels and the points anchored in data coordinates, mapped
+    /// into the chart by the coordinate function (snapped to the nearest of
+    /// the peaks, if enabled)
+    fn marks<T, X, Y>(
+        &self,
+        chart: &mut ChartContext<T, Cartesian2d<X, Y>>,
+        peaks: &[(f64, f64)],
+        coordinate: impl Fn(f64, f64) -> (X::ValueType, Y::ValueType),
+    ) -> Result<()>
+    where
+        T: DrawingBackend,
+        <T as DrawingBackend>::ErrorType: 'static,
+        X: Ranged,
+        Y: Ranged,
+    {
+        let style = self.label_style();
+        chart.draw_series(
+            self.labels
+                .iter()
+                .filter(|label| label.coordinates.anchor == Anchor::Data)
+                .map(|label| {
+                    let (x, y) = label.coordinates.snapped(peaks);
+                    EmptyElement::at(coordinate(x, y))
+                        + plotters::element::Text::new(label.text.clone(), (0, 0), style.clone())
+                }),
+        )?;
+        chart.draw_series(
+            self.points
+                .iter()
+                .filter(|point| point.coordinates.anchor == Anchor::Data)
+                .map(|point| {
+                    let (x, y) = point.coordinates.snapped(peaks);
+                    EmptyElement::at(coordinate(x, y))
+                        + Circle::new((0, 0), point.size, point.style())
+                }),
         )?;
-        // To avoid the IO failure being ignored silently, we manually call the present function
-        drawing_area.present().expect("Unable to write result to file, please make sure 'plotters-doc-data' dir exists under current dir");
+        Ok(())
+    }
+
+    /// Draws the mass differences as lines between their m/z, above the most
+    /// intense of the peaks in between and their labels, labelled with the
+    /// difference
+    fn draw_differences<T, X, Y>(
+        &self,
+        chart: &mut ChartContext<T, Cartesian2d<X, Y>>,
+        differences: &[Difference],
+        peaks: &[(f64, f64)],
+        style: &plotters::style::TextStyle,
+        coordinate: impl Fn(f64, f64) -> (X::ValueType, Y::ValueType),
+    ) -> Result<()>
+    where
+        T: DrawingBackend,
+        <T as DrawingBackend>::ErrorType: 'static,
+        X: Ranged,
+        Y: Ranged,
+    {
+        let stroke = BLACK.stroke_width(self.config.chart.axes.stroke_width);
+        // Ranges of the differences drawn and their levels
+        let mut levels: Vec<(f64, f64, usize)> = Vec::new();
+        for difference in differences {
+            let (start, end) = (
+                difference.from.min(difference.to),
+                difference.from.max(difference.to),
+            );
+            let top = peaks
+                .iter()
+                .filter(|(mass, _)| (start..=end).contains(mass))
+                .map(|&(_, intensity)| intensity)
+                .fold(0.0, f64::max);
+            let level = levels
+                .iter()
+                .filter(|&&(other_start, other_end, _)| other_start <= end && start <= other_end)
+                .map(|&(.., level)| level + 1)
+                .max()
+                .unwrap_or(0);
+            levels.push((start, end, level));
+            let text = difference.to_string();
+            let (_, height) = chart.plotting_area().estimate_text_size(&text, style)?;
+            // Over the labels of the peaks, then over the lower differences
+            let row = height as i32 + 2 * LABEL_GAP;
+            let y = -(row + level as i32 * row) - LABEL_GAP;
+            let from = chart.backend_coord(&coordinate(difference.from, top));
+            let to = chart.backend_coord(&coordinate(difference.to, top));
+            chart.draw_series([EmptyElement::at(coordinate(difference.from, top))
+                + PathElement::new(difference.line.path(to.0 - from.0, y), stroke)])?;
+            chart.draw_series([EmptyElement::at(coordinate(difference.from, top))
+                + plotters::element::Text::new(
+                    text,
+                    ((to.0 - from.0) / 2, y - LABEL_GAP),
+                    style.pos(Pos::new(HPos::Center, VPos::Bottom)),
+                )])?;
+        }
+        Ok(())
+    }
+
+    /// Mass differences, with the gaps of the interpretation of the spectrum
+    /// (if any)
+    fn differences(&self, index: usize) -> Vec<Difference> {
+        let mut differences = self.differences.clone();
+        if let Some(interpretation) = self.interpretation(index) {
+            differences.extend(
+                interpretation
+                    .positions
+                    .iter()
+                    .zip(&interpretation.gaps)
+                    .map(|(position, &(from, to))| Difference {
+                        from: from as _,
+                        to: to as _,
+                        line: Line::Bracket,
+                        text: format!("Δ{position}"),
+                    }),
+            );
+        }
+        differences
+    }
+
+    /// Marks the base ions and the ions of the series of the interpretation
+    /// of the spectrum (if any)
+    fn draw_diagnostic_ions<T, X, Y>(
+        &self,
+        chart: &mut ChartContext<T, Cartesian2d<X, Y>>,
+        index: usize,
+        peaks: &[(f64, f64)],
+        coordinate: impl Fn(f64, f64) -> (X::ValueType, Y::ValueType),
+    ) -> Result<()>
+    where
+        T: DrawingBackend,
+        <T as DrawingBackend>::ErrorType: 'static,
+        X: Ranged,
+        Y: Ranged,
+    {
+        let Some(interpretation) = self.interpretation(index) else {
+            return Ok(());
+        };
+        let masses = BASE_IONS
+            .into_iter()
+            .chain(interpretation.ions.iter().map(|ion| ion.mass))
+            .collect::<HashSet<_>>();
+        chart.draw_series(
+            peaks
+                .iter()
+                .filter(|(mass, _)| masses.contains(&(mass.round() as u64)))
+                .map(|&(mass, intensity)| {
+                    EmptyElement::at(coordinate(mass, intensity))
+                        + Circle::new((0, 0), DIAGNOSTIC_ION_SIZE, RED.filled())
+                }),
+        )?;
+        Ok(())
+    }
+
+    /// Interpretation of the spectrum (if any)
+    fn interpretation(&self, index: usize) -> Option<&Interpretation> {
+        let (spectrum, interpretation) = self.picolinyl.interpretation.as_ref()?;
+        (*spectrum == index).then_some(interpretation)
+    }
+
+    /// Marks the matched expected ions at their peaks and the missing ones on
+    /// the baseline, for the comparison of the spectrum (if any)
+    fn draw_comparison<T, X, Y>(
+        &self,
+        chart: &mut ChartContext<T, Cartesian2d<X, Y>>,
+        index: usize,
+        peaks: &[(f64, f64)],
+        coordinate: impl Fn(f64, f64) -> (X::ValueType, Y::ValueType),
+    ) -> Result<()>
+    where
+        T: DrawingBackend,
+        <T as DrawingBackend>::ErrorType: 'static,
+        X: Ranged,
+        Y: Ranged,
+    {
+        let Some(comparison) = self.comparison(index) else {
+            return Ok(());
+        };
+        let matched = comparison
+            .matched
+            .iter()
+            .map(|ion| ion.mass)
+            .collect::<HashSet<_>>();
+        chart.draw_series(
+            peaks
+                .iter()
+                .filter(|(mass, _)| matched.contains(&(mass.round() as u64)))
+                .map(|&(mass, intensity)| {
+                    EmptyElement::at(coordinate(mass, intensity))
+                        + Circle::new((0, 0), DIAGNOSTIC_ION_SIZE, GREEN.filled())
+                }),
+        )?;
+        chart.draw_series(comparison.missing.iter().map(|ion| {
+            EmptyElement::at(coordinate(ion.mass as _, 0.0))
+                + Cross::new((0, 0), DIAGNOSTIC_ION_SIZE, RED.stroke_width(2))
+        }))?;
+        Ok(())
+    }
+
+    /// Comparison of the spectrum with the expected ions (if any)
+    fn comparison(&self, index: usize) -> Option<&Comparison> {
+        let (spectrum, _, _, comparison) = self.theoretical.comparison.as_ref()?;
+        (*spectrum == index).then_some(comparison)
+    }
+
+    fn label_style(&self) -> plotters::style::TextStyle {
+        self.config
+            .chart
+            .axes
+            .labels
+            .font
+            .style()
+            .into_text_style(&self.config.chart.size)
+            .color(&BLACK)
+    }
+
+    fn annotation_style<'a, T: DrawingBackend>(
+        &'a self,
+        drawing_area: &DrawingArea<T, Shift>,
+    ) -> plotters::style::TextStyle<'a> {
+        self.config
+            .chart
+            .axes
+            .labels
+            .font
+            .style()
+            .into_text_style(drawing_area)
+            .pos(Pos::new(HPos::Center, VPos::Bottom))
+    }
+
+    fn builder<'a, T: DrawingBackend>(
+        &'a self,
+        drawing_area: &'a DrawingArea<T, Shift>,
+        caption: &'a str,
+    ) -> ChartBuilder<'a, 'a, T> {
+        let mut builder = ChartBuilder::on(drawing_area);
+        builder
+            .x_label_area_size(self.config.chart.axes.labels.x.area_size)
+            .y_label_area_size(self.config.chart.axes.labels.y.area_size)
+            .margin(self.config.chart.margin)
+            .caption(caption, self.config.chart.caption.font.style());
+        builder
+    }
+
+    fn mesh<T, X, Y>(&self, chart: &mut ChartContext<T, Cartesian2d<X, Y>>) -> Result<()>
+    where
+        T: DrawingBackend,
+        <T as DrawingBackend>::ErrorType: 'static,
+        X: Ranged + ValueFormatter<X::ValueType>,
+        Y: Ranged + ValueFormatter<Y::ValueType>,
+    {
+        chart
+            .configure_mesh()
+            .disable_mesh()
+            .x_desc(&self.config.chart.axes.descriptions.x)
+            .y_desc(&self.config.chart.axes.descriptions.y)
+            .axis_desc_style(self.config.chart.axes.descriptions.font.style())
+            .label_style(self.config.chart.axes.labels.font.style())
+            .x_labels(100)
+            .set_all_tick_mark_size(RelativeSize::Width(self.tick_mark_size))
+            .axis_style(BLACK.stroke_width(self.config.chart.axes.stroke_width))
+            .draw()?;
+        // chart.configure_series_labels().draw()?;
         Ok(())
     }
 
@@ -1072,14 +3219,20 @@ impl eframe::App for App {
         self.drag_and_drop_files(ctx);
         self.errors(ctx);
         self.files(ctx);
+        self.search(ctx);
+        self.heatmap(ctx);
+        self.clustering(ctx);
     }
 }
 
 mod config {
+    use crate::picking::Picker;
     use eframe::emath::Numeric;
     use serde::{Deserialize, Serialize};
     use std::{
         default::default,
+        fmt::{self, Display, Formatter},
+        hash::{Hash, Hasher},
         ops::{Range, RangeInclusive},
     };
 
@@ -1104,6 +3257,11 @@ mod config {
     /// Chart
     #[derive(Clone, Default, Deserialize, Serialize)]
     pub(super) struct Chart {
+        pub(super) annotations: bool,
+        /// m/z labels of the picked peaks
+        pub(super) peak_labels: bool,
+        pub(super) picker: Picker,
+        pub(super) arrangement: Arrangement,
         pub(super) axes: Axes,
         pub(super) bounds: Bounds,
         pub(super) caption: Caption,
@@ -1111,6 +3269,34 @@ mod config {
         pub(super) size: (u32, u32),
     }
 
+    /// Arrangement of the spectra
+    #[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+    pub(super) enum Arrangement {
+        /// Selected spectrum
+        #[default]
+        Single,
+        /// Every included spectrum on the same axes
+        Overlay,
+        /// One chart per included spectrum, sharing the m/z axis
+        Stack,
+        /// Every included spectrum offset from the previous one
+        Waterfall { x: f64, y: f64 },
+        /// Selected spectrum up and the other one (index) inverted
+        Mirror(usize),
+    }
+
+    impl Display for Arrangement {
+        fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+            match self {
+                Self::Single => f.write_str("Single"),
+                Self::Overlay => f.write_str("Overlay"),
+                Self::Stack => f.write_str("Stack"),
+                Self::Waterfall { .. } => f.write_str("Waterfall"),
+                Self::Mirror(_) => f.write_str("Mirror"),
+            }
+        }
+    }
+
     /// Axes
     #[derive(Clone, Deserialize, Serialize)]
     pub(super) struct Axes {
@@ -1137,17 +3323,31 @@ mod config {
     // }
 
     /// Bounds
-    #[derive(Clone, Debug, Deserialize, Hash, Serialize)]
+    #[derive(Clone, Debug, Deserialize, Serialize)]
     pub(super) struct Bounds {
-        pub(super) x: Bound<u64>,
-        pub(super) y: Bound<u64>,
+        pub(super) x: Bound<f64>,
+        pub(super) y: Bound<f64>,
     }
 
     impl Default for Bounds {
         fn default() -> Self {
             Self {
-                x: Bound { start: 0, end: 100 },
-                y: Bound { start: 0, end: 100 },
+                x: Bound {
+                    start: 0.0,
+                    end: 100.0,
+                },
+                y: Bound {
+                    start: 0.0,
+                    end: 100.0,
+                },
+            }
+        }
+    }
+
+    impl Hash for Bounds {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            for value in [self.x.start, self.x.end, self.y.start, self.y.end] {
+                value.to_bits().hash(state);
             }
         }
     }
@@ -1169,6 +3369,13 @@ mod config {
         }
     }
 
+    impl Bound<f64> {
+        /// Nominal masses of the range
+        pub(super) fn nominal(&self) -> Range<u64> {
+            self.start.floor() as u64..self.end.ceil() as u64
+        }
+    }
+
     impl<T: Numeric> Bound<T> {
         pub(super) fn center(&self) -> f64 {
             self.start.to_f64() + self.end.to_f64() / 2.0
@@ -1277,28 +3484,85 @@ mod config {
     }
 }
 
-/// Line
-enum Line {
-    Horizontal(HLine),
-    Vertical(VLine),
-    Diagonal(DLine),
+/// Mass difference between two peaks
+#[derive(Clone, Default, Deserialize, Serialize)]
+struct Difference {
+    /// m/z
+    from: f64,
+    /// m/z
+    to: f64,
+    line: Line,
+    /// Neutral loss (formula)
+    text: String,
 }
 
-impl From<HLine> for Line {
-    fn from(value: HLine) -> Self {
-        Self::Horizontal(value)
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let difference = self.to - self.from;
+        let sign = if difference < 0.0 { '−' } else { '+' };
+        if difference.is_approx_integer() {
+            write!(f, "{sign}{}", difference.abs().round())?;
+        } else {
+            write!(f, "{sign}{:.2}", difference.abs())?;
+        }
+        if !self.text.is_empty() {
+            write!(f, " {}", self.text)?;
+        }
+        Ok(())
     }
 }
 
-impl From<VLine> for Line {
-    fn from(value: VLine) -> Self {
-        Self::Vertical(value)
+/// Line of a mass difference
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+enum Line {
+    Horizontal,
+    /// Horizontal with ticks down at the ends
+    #[default]
+    Bracket,
+    /// Horizontal, pointing to the second m/z
+    Arrow,
+}
+
+impl Line {
+    /// Arrow head and bracket tick size (pixels)
+    const SIZE: i32 = 6;
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Self::Horizontal => "—",
+            Self::Bracket => "⊓",
+            Self::Arrow => "→",
+        }
+    }
+
+    /// Path from the start, the end offset by the length (pixels), at the
+    /// height
+    fn path(self, length: i32, y: i32) -> Vec<(i32, i32)> {
+        let size = Self::SIZE;
+        match self {
+            Self::Horizontal => vec![(0, y), (length, y)],
+            Self::Bracket => vec![(0, y + size), (0, y), (length, y), (length, y + size)],
+            Self::Arrow => {
+                let back = length - length.signum() * size;
+                vec![
+                    (0, y),
+                    (length, y),
+                    (back, y - size / 2),
+                    (length, y),
+                    (back, y + size / 2),
+                ]
+            }
+        }
     }
 }
 
-impl From<DLine> for Line {
-    fn from(value: DLine) -> Self {
-        Self::Diagonal(value)
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Horizontal => f.write_str("Line"),
+            Self::Bracket => f.write_str("Bracket"),
+            Self::Arrow => f.write_str("Arrow"),
+        }
     }
 }
 
@@ -1317,7 +3581,166 @@ impl Center for Range<u64> {
 #[derive(Debug, Default)]
 struct Errors {
     show: bool,
-    buffer: IndexMap<usize, Error>,
+    buffer: IndexMap<usize, Vec<Error>>,
+}
+
+/// File export
+#[derive(Debug, Default)]
+struct Export {
+    path: String,
+    /// Replace the file if it exists
+    overwrite: bool,
+    error: Option<Error>,
+}
+
+/// Library search
+#[derive(Debug)]
+struct Search {
+    show: bool,
+    path: String,
+    libraries: Vec<Library>,
+    /// Number of hits
+    count: usize,
+    hits: Vec<Hit>,
+    /// Spectrum of the hit opened as a mirror comparison
+    opened: Option<usize>,
+    error: Option<Error>,
+}
+
+impl Default for Search {
+    fn default() -> Self {
+        Self {
+            show: false,
+            path: String::new(),
+            libraries: Vec::new(),
+            count: 10,
+            hits: Vec::new(),
+            opened: None,
+            error: None,
+        }
+    }
+}
+
+/// Similarity matrix
+#[derive(Debug)]
+struct Heatmap {
+    show: bool,
+    /// Cluster threshold
+    threshold: f64,
+    /// Spectra in the order of the rows
+    spectra: Vec<usize>,
+    matrix: Array2<f64>,
+    export: Export,
+}
+
+impl Default for Heatmap {
+    fn default() -> Self {
+        Self {
+            show: false,
+            threshold: 0.9,
+            spectra: Vec::new(),
+            matrix: Array2::zeros((0, 0)),
+            export: Export::default(),
+        }
+    }
+}
+
+/// Hierarchical clustering
+#[derive(Debug, Default)]
+struct Clustering {
+    show: bool,
+    linkage: Linkage,
+    /// Spectra of the leaves
+    spectra: Vec<usize>,
+    dendrogram: Dendrogram,
+}
+
+/// Background subtraction
+#[derive(Debug)]
+struct Subtraction {
+    /// Background spectrum
+    background: usize,
+    scale: Scale,
+    error: Option<Error>,
+}
+
+impl Default for Subtraction {
+    fn default() -> Self {
+        Self {
+            background: 0,
+            scale: Scale::Factor(1.0),
+            error: None,
+        }
+    }
+}
+
+/// Consensus spectrum
+#[derive(Debug)]
+struct Consensus {
+    /// Alignment tolerance (m/z)
+    tolerance: f64,
+    error: Option<Error>,
+}
+
+impl Default for Consensus {
+    fn default() -> Self {
+        Self {
+            tolerance: 0.5,
+            error: None,
+        }
+    }
+}
+
+/// Picolinyl ester interpretation
+#[derive(Debug, Default)]
+struct Picolinyl {
+    given: Given,
+    /// Spectrum and its interpretation
+    interpretation: Option<(usize, Interpretation)>,
+    error: Option<Error>,
+}
+
+/// Chain given by its length or its molecular ion
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Given {
+    Carbons(u64),
+    MolecularIon(u64),
+}
+
+impl Default for Given {
+    fn default() -> Self {
+        Self::Carbons(18)
+    }
+}
+
+/// Theoretical spectrum of a fatty acid derivative
+#[derive(Debug)]
+struct Theoretical {
+    derivative: Derivative,
+    carbons: u64,
+    /// Comma separated double bond positions
+    double_bonds: String,
+    /// Comma separated methyl branch positions
+    branches: String,
+    /// Minimal intensity of the matched ions relative to the base peak
+    threshold: f64,
+    /// Spectrum, fatty acid, derivative and their comparison
+    comparison: Option<(usize, FattyAcid, Derivative, Comparison)>,
+    error: Option<Error>,
+}
+
+impl Default for Theoretical {
+    fn default() -> Self {
+        Self {
+            derivative: Derivative::Picolinyl,
+            carbons: 18,
+            double_bonds: String::new(),
+            branches: String::new(),
+            threshold: 0.01,
+            comparison: None,
+            error: None,
+        }
+    }
 }
 
 /// Label
@@ -1338,6 +3761,21 @@ struct Point {
     coordinates: Coordinates,
 }
 
+impl Point {
+    fn style(&self) -> ShapeStyle {
+        ShapeStyle {
+            color: RGBAColor(
+                self.color.r(),
+                self.color.g(),
+                self.color.b(),
+                self.color.a() as f64 / u8::MAX as f64,
+            ),
+            filled: self.filled,
+            stroke_width: default(),
+        }
+    }
+}
+
 enum Shape {
     Circle,
     Diamond,
@@ -1356,6 +3794,51 @@ enum Shape {
 struct Coordinates {
     x: f64,
     y: f64,
+    anchor: Anchor,
+    /// Snap to the nearest peak (data anchor)
+    snap: bool,
+}
+
+impl Coordinates {
+    /// Anchor and snapping controls
+    fn ui(&mut self, ui: &mut Ui) {
+        ui.selectable_value(&mut self.anchor, Anchor::Pixel, "px")
+            .on_hover_text("Anchored in pixels");
+        ui.selectable_value(&mut self.anchor, Anchor::Data, "data")
+            .on_hover_text("Anchored in data (m/z, intensity)");
+        ui.add_enabled_ui(self.anchor == Anchor::Data, |ui| {
+            ui.toggle_value(&mut self.snap, "snap")
+                .on_hover_text("Snap to the nearest peak");
+        });
+    }
+
+    /// Coordinates of the nearest peak (the most intense of the equally near
+    /// ones), if snapping
+    fn snapped(&self, peaks: &[(f64, f64)]) -> (f64, f64) {
+        if !self.snap {
+            return (self.x, self.y);
+        }
+        peaks
+            .iter()
+            .copied()
+            .min_by(|left, right| {
+                (left.0 - self.x)
+                    .abs()
+                    .total_cmp(&(right.0 - self.x).abs())
+                    .then_with(|| right.1.abs().total_cmp(&left.1.abs()))
+            })
+            .unwrap_or((self.x, self.y))
+    }
+}
+
+/// Anchor of the coordinates
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+enum Anchor {
+    /// Drawing area pixels
+    #[default]
+    Pixel,
+    /// Chart data (m/z, intensity)
+    Data,
 }
 
 // /// Visuals
@@ -1441,4 +3924,6 @@ struct Chart {
 // }
 
 mod bounder;
+mod layout;
 mod normalizer;
+mod order;
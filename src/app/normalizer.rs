@@ -1,20 +1,22 @@
+use crate::parser::Peaks;
 use egui::util::cache::{ComputerMut, FrameCache};
+use noisy_float::types::N64;
 use std::collections::BTreeMap;
 
 /// Normalized
-pub(super) type Normalized = FrameCache<BTreeMap<u64, f64>, Normalizer>;
+pub(super) type Normalized = FrameCache<BTreeMap<N64, f64>, Normalizer>;
 
 /// Normalizer
 #[derive(Default)]
 pub(super) struct Normalizer;
 
-impl ComputerMut<(&BTreeMap<u64, u64>, bool), BTreeMap<u64, f64>> for Normalizer {
-    fn compute(&mut self, (peaks, percent): (&BTreeMap<u64, u64>, bool)) -> BTreeMap<u64, f64> {
-        let max = peaks.values().max().copied().unwrap_or_default();
+impl ComputerMut<(&Peaks, bool), BTreeMap<N64, f64>> for Normalizer {
+    fn compute(&mut self, (peaks, percent): (&Peaks, bool)) -> BTreeMap<N64, f64> {
+        let max = peaks.values().max().map_or(0.0, |max| max.raw());
         peaks
             .iter()
             .map(|(&mass, &intensity)| {
-                let mut intensity = intensity as f64 / max as f64;
+                let mut intensity = intensity.raw() / max;
                 if percent {
                     intensity *= 100.0;
                 }
@@ -0,0 +1,128 @@
+use super::molecular_ion;
+use crate::parser::Parsed;
+use indexmap::IndexMap;
+use itertools::Itertools;
+use std::{cmp::Ordering, collections::HashMap};
+
+/// Sorts the spectra in the order given, the others after them
+pub(super) fn sort<T>(spectra: &mut IndexMap<usize, T>, order: &[usize]) {
+    let positions = order
+        .iter()
+        .enumerate()
+        .map(|(position, &index)| (index, position))
+        .collect::<HashMap<_, _>>();
+    let position = |index: &usize| positions.get(index).copied().unwrap_or(usize::MAX);
+    spectra.sort_by(|left, _, right, _| position(left).cmp(&position(right)));
+}
+
+/// Sorts the spectra by retention time, the ones without one first
+pub(super) fn by_retention_time(spectra: &mut IndexMap<usize, Parsed>) {
+    spectra.sort_by(|_, left, _, right| {
+        left.retention_time
+            .partial_cmp(&right.retention_time)
+            .unwrap_or(Ordering::Equal)
+    });
+}
+
+/// Sorts the spectra by the molecular ions of their filenames, the ones without
+/// one first
+pub(super) fn by_molecular_ion(spectra: &mut IndexMap<usize, Parsed>) {
+    spectra.sort_by(|_, left, _, right| molecular_ion(left).cmp(&molecular_ion(right)));
+}
+
+/// Groups of the Files window: the files in the order of their first spectra
+/// (the ones without spectra last), then the derived spectra (`None`), if any
+pub(super) fn groups<'a>(
+    spectra: impl IntoIterator<Item = &'a usize>,
+    sources: &HashMap<usize, usize>,
+    files: usize,
+) -> Vec<Option<usize>> {
+    let (mut groups, derived) = spectra
+        .into_iter()
+        .map(|index| sources.get(index).copied())
+        .unique()
+        .partition::<Vec<_>, _>(Option::is_some);
+    for file in 0..files {
+        if !groups.contains(&Some(file)) {
+            groups.push(Some(file));
+        }
+    }
+    groups.extend(derived);
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        clustering::{cluster, Linkage},
+        template::Templates,
+    };
+    use ndarray::array;
+    use uom::si::{f64::Time, time::minute};
+
+    #[test]
+    fn groups() {
+        let sources = HashMap::from([(0, 1), (1, 0), (2, 1)]);
+        assert_eq!(
+            super::groups(&[2, 3, 1, 0], &sources, 3),
+            [Some(1), Some(0), Some(2), None],
+        );
+        assert_eq!(super::groups(&[], &sources, 2), [Some(0), Some(1)]);
+    }
+
+    #[test]
+    fn dendrogram() {
+        // A file per spectrum, the first and the last ones alike
+        let mut spectra = IndexMap::from([(0, "A"), (1, "B"), (2, "C")]);
+        let sources = HashMap::from([(0, 0), (1, 1), (2, 2)]);
+        let distances = array![[0.0, 0.9, 0.1], [0.9, 0.0, 0.9], [0.1, 0.9, 0.0]];
+        let leaves = cluster(&distances, Linkage::Average).order();
+        sort(&mut spectra, &leaves);
+        assert_eq!(
+            super::groups(spectra.keys(), &sources, 3),
+            leaves.into_iter().map(Some).collect::<Vec<_>>(),
+        );
+        assert_eq!(spectra.values().copied().collect::<String>(), "ACB");
+    }
+
+    #[test]
+    fn retention_time() {
+        // A file per spectrum, in drop order
+        let mut spectra = [Some(3.0), None, Some(1.0)]
+            .into_iter()
+            .map(|minutes| Parsed {
+                retention_time: minutes.map(Time::new::<minute>),
+                ..Parsed::default()
+            })
+            .enumerate()
+            .collect::<IndexMap<_, _>>();
+        let sources = HashMap::from([(0, 0), (1, 1), (2, 2)]);
+        by_retention_time(&mut spectra);
+        assert_eq!(
+            super::groups(spectra.keys(), &sources, 3),
+            [Some(1), Some(2), Some(0)],
+        );
+    }
+
+    #[test]
+    fn molecular_ion() {
+        // A file per spectrum, in drop order
+        let templates = Templates::default();
+        let mut spectra = ["16_1-345", "Blank #1", "14_0-299"]
+            .into_iter()
+            .map(|stem| {
+                let mut parsed = Parsed::default();
+                templates.extract(stem).unwrap().apply(&mut parsed);
+                parsed
+            })
+            .enumerate()
+            .collect::<IndexMap<_, _>>();
+        let sources = HashMap::from([(0, 0), (1, 1), (2, 2)]);
+        by_molecular_ion(&mut spectra);
+        assert_eq!(
+            super::groups(spectra.keys(), &sources, 3),
+            [Some(1), Some(2), Some(0)],
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use ndarray::Array2;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+
+/// Agglomerative hierarchical clustering
+///
+/// Repeatedly merges the two closest clusters, the distance between clusters
+/// given by the linkage.
+pub fn cluster(distances: &Array2<f64>, linkage: Linkage) -> Dendrogram {
+    let leaves = distances.nrows();
+    let mut distances = distances.clone();
+    // Active clusters: identifier and size, by row
+    let mut clusters = (0..leaves).map(|leaf| Some((leaf, 1))).collect::<Vec<_>>();
+    let mut merges = Vec::with_capacity(leaves.saturating_sub(1));
+    for identifier in leaves..leaves + leaves.saturating_sub(1) {
+        let mut closest = None;
+        for row in 0..leaves {
+            for column in row + 1..leaves {
+                if clusters[row].is_none() || clusters[column].is_none() {
+                    continue;
+                }
+                let distance = distances[(row, column)];
+                if !closest.is_some_and(|(_, _, closest)| closest <= distance) {
+                    closest = Some((row, column, distance));
+                }
+            }
+        }
+        let Some((row, column, distance)) = closest else {
+            break;
+        };
+        let (Some((left, left_size)), Some((right, right_size))) =
+            (clusters[row], clusters[column])
+        else {
+            unreachable!();
+        };
+        // The merged cluster takes the row of the left one
+        for other in 0..leaves {
+            if other == row || other == column || clusters[other].is_none() {
+                continue;
+            }
+            let (left_distance, right_distance) =
+                (distances[(row, other)], distances[(column, other)]);
+            let distance = match linkage {
+                Linkage::Average => {
+                    (left_distance * left_size as f64 + right_distance * right_size as f64)
+                        / (left_size + right_size) as f64
+                }
+                Linkage::Complete => left_distance.max(right_distance),
+                Linkage::Single => left_distance.min(right_distance),
+            };
+            distances[(row, other)] = distance;
+            distances[(other, row)] = distance;
+        }
+        clusters[row] = Some((identifier, left_size + right_size));
+        clusters[column] = None;
+        merges.push(Merge {
+            left,
+            right,
+            distance,
+            size: left_size + right_size,
+        });
+    }
+    Dendrogram { leaves, merges }
+}
+
+/// Linkage
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub enum Linkage {
+    /// Mean distance between the members (UPGMA)
+    #[default]
+    Average,
+    /// Greatest distance between the members
+    Complete,
+    /// Least distance between the members
+    Single,
+}
+
+impl Display for Linkage {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Average => f.write_str("Average"),
+            Self::Complete => f.write_str("Complete"),
+            Self::Single => f.write_str("Single"),
+        }
+    }
+}
+
+/// Dendrogram
+///
+/// Leaves are clusters `0..leaves`, the merge `index` makes the cluster
+/// `leaves + index`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Dendrogram {
+    pub leaves: usize,
+    pub merges: Vec<Merge>,
+}
+
+impl Dendrogram {
+    /// Leaves from left to right
+    pub fn order(&self) -> Vec<usize> {
+        let mut order = Vec::with_capacity(self.leaves);
+        let mut stack = match self.merges.len() {
+            0 => (0..self.leaves).rev().collect(),
+            length => vec![self.leaves + length - 1],
+        };
+        while let Some(cluster) = stack.pop() {
+            match cluster.checked_sub(self.leaves) {
+                Some(merge) => {
+                    let merge = &self.merges[merge];
+                    stack.push(merge.right);
+                    stack.push(merge.left);
+                }
+                None => order.push(cluster),
+            }
+        }
+        order
+    }
+}
+
+/// Merge of two clusters
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Merge {
+    pub left: usize,
+    pub right: usize,
+    pub distance: f64,
+    /// Number of leaves
+    pub size: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::array;
+
+    // Points 0, 1, 5 and 6 on a line
+    fn distances() -> Array2<f64> {
+        let points = [0.0, 1.0, 5.0, 6.0];
+        Array2::from_shape_fn((4, 4), |(row, column)| {
+            f64::abs(points[row] - points[column])
+        })
+    }
+
+    #[test]
+    fn linkages() {
+        for (linkage, root) in [
+            (Linkage::Average, 5.0),
+            (Linkage::Complete, 6.0),
+            (Linkage::Single, 4.0),
+        ] {
+            let dendrogram = cluster(&distances(), linkage);
+            assert_eq!(dendrogram.merges.len(), 3, "{linkage}");
+            assert_eq!(dendrogram.merges[0].distance, 1.0);
+            assert_eq!(dendrogram.merges[1].distance, 1.0);
+            let last = dendrogram.merges[2];
+            assert_eq!((last.distance, last.size), (root, 4), "{linkage}");
+            assert_eq!(dendrogram.order(), [0, 1, 2, 3]);
+        }
+    }
+
+    #[test]
+    fn order() {
+        let distances = array![[0.0, 9.0, 1.0], [9.0, 0.0, 9.0], [1.0, 9.0, 0.0]];
+        let dendrogram = cluster(&distances, Linkage::Average);
+        assert_eq!(dendrogram.merges[0].left, 0);
+        assert_eq!(dendrogram.merges[0].right, 2);
+        assert_eq!(dendrogram.order(), [0, 2, 1]);
+    }
+
+    #[test]
+    fn empty() {
+        let dendrogram = cluster(&Array2::zeros((0, 0)), Linkage::Single);
+        assert!(dendrogram.merges.is_empty());
+        assert!(dendrogram.order().is_empty());
+        let dendrogram = cluster(&Array2::zeros((1, 1)), Linkage::Single);
+        assert_eq!(dendrogram.order(), [0]);
+    }
+}
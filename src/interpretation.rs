@@ -0,0 +1,232 @@
+use crate::parser::Peaks;
+use noisy_float::types::n64;
+use std::fmt::{self, Display, Formatter};
+
+/// Base ions of the picolinyl esters
+pub const BASE_IONS: [u64; 4] = [92, 108, 151, 164];
+
+/// Carbons of the first ion of the series (m/z 164)
+const FIRST: u64 = 3;
+
+/// Fatty acid chain
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Chain {
+    pub carbons: u64,
+    pub double_bonds: u64,
+}
+
+impl Chain {
+    /// Chain of the picolinyl ester molecular ion
+    pub fn from_molecular_ion(mass: u64) -> Option<Self> {
+        let methylenes = mass.checked_sub(123)?;
+        let carbons = methylenes.div_ceil(14);
+        let double_bonds = (14 * carbons - methylenes) / 2;
+        ((14 * carbons - methylenes) % 2 == 0 && carbons > FIRST).then_some(Self {
+            carbons,
+            double_bonds,
+        })
+    }
+
+    /// Chain of the carbons, with the number of double bonds of the most
+    /// intense molecular ion candidate
+    pub fn from_carbons(peaks: &Peaks, carbons: u64) -> Self {
+        let double_bonds = (0..=carbons.saturating_sub(FIRST + 1) / 2)
+            .max_by(|&left, &right| {
+                let intensity = |double_bonds| {
+                    intensity(
+                        peaks,
+                        Self {
+                            carbons,
+                            double_bonds,
+                        }
+                        .molecular_ion(),
+                    )
+                };
+                // The less unsaturated of the equally intense ones
+                intensity(left)
+                    .total_cmp(&intensity(right))
+                    .then(right.cmp(&left))
+            })
+            .unwrap_or_default();
+        Self {
+            carbons,
+            double_bonds,
+        }
+    }
+
+    /// Picolinyl ester molecular ion
+    pub fn molecular_ion(&self) -> u64 {
+        (123 + 14 * self.carbons).saturating_sub(2 * self.double_bonds)
+    }
+}
+
+impl Display for Chain {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.carbons, self.double_bonds)
+    }
+}
+
+/// Interpretation of a picolinyl ester spectrum
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Interpretation {
+    pub chain: Chain,
+    /// Double bond positions (Δ, from the carboxyl)
+    pub positions: Vec<u64>,
+    /// Ions of the series, in increasing order
+    pub ions: Vec<Ion>,
+    /// Gaps of 26 across the double bonds (m/z to m/z)
+    pub gaps: Vec<(u64, u64)>,
+}
+
+impl Display for Interpretation {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if !self.positions.is_empty() {
+            write!(
+                f,
+                "Δ{} ",
+                self.positions
+                    .iter()
+                    .map(u64::to_string)
+                    .collect::<Vec<_>>()
+                    .join(",")
+            )?;
+        }
+        write!(f, "{}", self.chain)
+    }
+}
+
+/// Ion of the series: the fragment of the carbons from the carboxyl
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Ion {
+    pub carbons: u64,
+    pub mass: u64,
+    pub intensity: f64,
+}
+
+/// Locates the double bonds of a picolinyl ester by the 26 amu gaps of its ion
+/// series
+pub fn picolinyl(peaks: &Peaks, chain: Chain) -> Interpretation {
+    let last = chain.carbons.saturating_sub(1);
+    let double_bonds = chain.double_bonds as usize;
+    // Best total intensity and positions of the ions from the carbons on, by
+    // the double bonds before them and whether the previous carbon has one
+    let mut best = vec![[None, None]; double_bonds + 1];
+    best[double_bonds] = [Some((0.0, Vec::new())), Some((0.0, Vec::new()))];
+    for carbons in (FIRST..=last).rev() {
+        let next = best;
+        best = vec![[None, None]; double_bonds + 1];
+        for before in 0..=double_bonds {
+            for previous in [false, true] {
+                let mass = 122 + 14 * carbons - 2 * before as u64;
+                let saturated = next[before][0]
+                    .clone()
+                    .map(|(score, positions)| (score + intensity(peaks, mass), positions));
+                let unsaturated =
+                    (!previous && before < double_bonds && carbons > FIRST && carbons < last)
+                        .then(|| next[before + 1][1].clone())
+                        .flatten()
+                        .map(|(score, mut positions): (f64, Vec<u64>)| {
+                            positions.insert(0, carbons);
+                            (score, positions)
+                        });
+                best[before][previous as usize] = match (saturated, unsaturated) {
+                    (Some(saturated), Some(unsaturated)) if unsaturated.0 > saturated.0 => {
+                        Some(unsaturated)
+                    }
+                    (saturated, unsaturated) => saturated.or(unsaturated),
+                };
+            }
+        }
+    }
+    let positions = best[0][0]
+        .take()
+        .map_or_else(Vec::new, |(_, positions)| positions);
+    let ion = |carbons: u64| {
+        let before = positions
+            .iter()
+            .filter(|&&position| position < carbons)
+            .count() as u64;
+        122 + 14 * carbons - 2 * before
+    };
+    let ions = (FIRST..=last)
+        .filter(|carbons| !positions.contains(carbons))
+        .map(|carbons| Ion {
+            carbons,
+            mass: ion(carbons),
+            intensity: intensity(peaks, ion(carbons)),
+        })
+        .collect();
+    let gaps = positions
+        .iter()
+        .map(|&position| (ion(position - 1), ion(position + 1)))
+        .collect();
+    Interpretation {
+        chain,
+        positions,
+        ions,
+        gaps,
+    }
+}
+
+/// Most intense peak of the nominal mass
+pub(crate) fn intensity(peaks: &Peaks, mass: u64) -> f64 {
+    peaks
+        .range(n64(mass as f64 - 0.5)..n64(mass as f64 + 0.5))
+        .map(|(_, intensity)| intensity.raw())
+        .fold(0.0, f64::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+    use std::{fs::read_to_string, path::Path};
+
+    fn load(path: &str) -> Peaks {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("input")
+            .join(path);
+        parse(&read_to_string(path).unwrap()).unwrap().peaks
+    }
+
+    #[test]
+    fn chain() {
+        assert_eq!(
+            Chain::from_molecular_ion(345),
+            Some(Chain {
+                carbons: 16,
+                double_bonds: 1,
+            }),
+        );
+        assert_eq!(Chain::from_molecular_ion(371).unwrap().to_string(), "18:2");
+        assert_eq!(
+            Chain::from_carbons(&load("1/9,12-18_2.MSP"), 18).double_bonds,
+            2
+        );
+    }
+
+    #[test]
+    fn positions() {
+        for (path, carbons) in [("1/9,12_16_2.MSP", 16), ("1/9,12-17_2.MSP", 17)] {
+            let peaks = load(path);
+            let chain = Chain {
+                carbons,
+                double_bonds: 2,
+            };
+            assert_eq!(picolinyl(&peaks, chain).positions, [9, 12]);
+        }
+        let interpretation = picolinyl(
+            &load("1/9,12-18_2.MSP"),
+            Chain::from_molecular_ion(371).unwrap(),
+        );
+        assert_eq!(interpretation.positions, [9, 12]);
+        assert_eq!(interpretation.gaps, [(234, 260), (274, 300)]);
+        assert_eq!(interpretation.to_string(), "Δ9,12 18:2");
+        assert_eq!(interpretation.ions[0].mass, 164);
+        let interpretation = picolinyl(
+            &load("16_1-345.MSP"),
+            Chain::from_molecular_ion(345).unwrap(),
+        );
+        assert_eq!(interpretation.positions, [9]);
+    }
+}
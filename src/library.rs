@@ -0,0 +1,105 @@
+use crate::{
+    parser::{parse_library, Parsed},
+    similarity::{match_factor, reverse_match_factor},
+    utils::decode,
+};
+use anyhow::Result;
+use std::{
+    cmp::Ordering,
+    fs::read,
+    path::{Path, PathBuf},
+};
+use tracing::warn;
+
+/// Spectral library
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Library {
+    pub path: PathBuf,
+    pub spectra: Vec<Parsed>,
+}
+
+impl Library {
+    /// Reads a MSP library, skipping malformed records
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let (content, _) = decode(&read(path)?, None)?;
+        let spectra = parse_library(&content)
+            .into_iter()
+            .filter_map(|parsed| {
+                parsed
+                    .map_err(|error| warn!(path = %path.display(), %error))
+                    .ok()
+            })
+            .collect();
+        Ok(Self {
+            path: path.to_owned(),
+            spectra,
+        })
+    }
+}
+
+/// Library hit
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hit {
+    /// Index of the library
+    pub library: usize,
+    /// Index of the spectrum in the library
+    pub spectrum: usize,
+    pub match_factor: f64,
+    pub reverse_match_factor: f64,
+}
+
+/// Best hits of the unknown spectrum, by match factor
+pub fn search(unknown: &Parsed, libraries: &[Library], count: usize) -> Vec<Hit> {
+    let mut hits = Vec::new();
+    for (library, spectra) in libraries.iter().enumerate() {
+        for (spectrum, parsed) in spectra.spectra.iter().enumerate() {
+            hits.push(Hit {
+                library,
+                spectrum,
+                match_factor: match_factor(unknown, parsed),
+                reverse_match_factor: reverse_match_factor(unknown, parsed),
+            });
+        }
+    }
+    hits.sort_by(|left, right| {
+        right
+            .match_factor
+            .partial_cmp(&left.match_factor)
+            .unwrap_or(Ordering::Equal)
+    });
+    hits.truncate(count);
+    hits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn load() {
+        let library =
+            Library::load(Path::new(env!("CARGO_MANIFEST_DIR")).join("input/1.msp")).unwrap();
+        assert!(!library.spectra.is_empty());
+    }
+
+    #[test]
+    fn search() {
+        let spectra = [
+            "Name: A\nNum Peaks: 2\n41 100; 43 50;\n",
+            "Name: B\nNum Peaks: 2\n41 50; 43 100;\n",
+            "Name: C\nNum Peaks: 1\n57 100;\n",
+        ];
+        let libraries = [Library {
+            path: PathBuf::new(),
+            spectra: spectra.map(|input| parse(input).unwrap()).to_vec(),
+        }];
+        let unknown = parse("Name: X\nNum Peaks: 2\n41 45; 43 100;\n").unwrap();
+        let hits = super::search(&unknown, &libraries, 2);
+        assert_eq!(hits.len(), 2);
+        assert_eq!((hits[0].library, hits[0].spectrum), (0, 1));
+        assert_eq!(hits[1].spectrum, 0);
+        assert!(hits[0].match_factor > hits[1].match_factor);
+    }
+}
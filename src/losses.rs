@@ -0,0 +1,66 @@
+/// Common neutral losses from molecular and fragment ions (nominal mass,
+/// formulas)
+pub const LOSSES: &[(u64, &str)] = &[
+    (1, "H"),
+    (2, "H2"),
+    (15, "CH3"),
+    (16, "O, NH2, CH4"),
+    (17, "OH, NH3"),
+    (18, "H2O"),
+    (19, "F"),
+    (20, "HF"),
+    (26, "C2H2"),
+    (27, "HCN, C2H3"),
+    (28, "CO, C2H4"),
+    (29, "CHO, C2H5"),
+    (30, "CH2O, NO"),
+    (31, "OCH3"),
+    (32, "CH3OH, S"),
+    (33, "SH"),
+    (34, "H2S"),
+    (35, "Cl"),
+    (36, "HCl"),
+    (42, "CH2CO, C3H6"),
+    (43, "CH3CO, C3H7"),
+    (44, "CO2, C3H8"),
+    (45, "COOH, OC2H5"),
+    (46, "NO2, C2H5OH"),
+    (56, "C4H8"),
+    (57, "C4H9"),
+    (59, "COOCH3"),
+    (60, "CH3COOH"),
+    (73, "(CH3)3Si"),
+    (77, "C6H5"),
+    (79, "Br"),
+    (80, "HBr"),
+    (90, "(CH3)3SiOH"),
+    (91, "C7H7"),
+    (127, "I"),
+    (128, "HI"),
+];
+
+/// Formulas of the common neutral loss of the (nominal) mass difference
+pub fn loss(difference: f64) -> Option<&'static str> {
+    let difference = difference.abs().round() as u64;
+    LOSSES
+        .binary_search_by_key(&difference, |&(mass, _)| mass)
+        .ok()
+        .map(|index| LOSSES[index].1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sorted() {
+        assert!(LOSSES.windows(2).all(|window| window[0].0 < window[1].0));
+    }
+
+    #[test]
+    fn losses() {
+        assert_eq!(loss(15.0), Some("CH3"));
+        assert_eq!(loss(-18.01), Some("H2O"));
+        assert_eq!(loss(14.0), None);
+    }
+}
@@ -45,8 +45,18 @@ fn main() {
 }
 
 mod app;
+mod clustering;
+mod interpretation;
+mod library;
+mod losses;
+mod operations;
 mod parser;
+mod picking;
+mod similarity;
+mod template;
+mod theoretical;
 mod utils;
+mod writer;
 
 mod tests {
     // use uom::si::{
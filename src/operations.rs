@@ -0,0 +1,186 @@
+use crate::{parser::Parsed, utils::stats::Summary};
+use anyhow::{bail, Result};
+use itertools::Itertools;
+use noisy_float::types::{n64, N64};
+
+/// Subtracts the scaled background from the sample
+///
+/// Peaks are matched by mass, and the ones that are no longer positive are
+/// dropped. The result is a copy of the sample, without its deviations, with
+/// the background recorded in the metadata.
+pub fn subtract(sample: &Parsed, background: &Parsed, scale: Scale) -> Result<Parsed> {
+    let factor = match scale {
+        Scale::Factor(factor) => factor,
+        Scale::Ion(mass) => {
+            let (Some(sample), Some(background)) =
+                (sample.peaks.get(&mass), background.peaks.get(&mass))
+            else {
+                bail!("reference ion {mass} is missing");
+            };
+            if *background == 0.0 {
+                bail!("reference ion {mass} has no intensity in the background");
+            }
+            (*sample / *background).raw()
+        }
+    };
+    let mut output = sample.clone();
+    output.name = format!("{} - {}", sample.name, background.name);
+    output.peaks = sample
+        .peaks
+        .iter()
+        .filter_map(|(&mass, &intensity)| {
+            let subtrahend = background
+                .peaks
+                .get(&mass)
+                .map_or(0.0, |background| background.raw() * factor);
+            let intensity = intensity.raw() - subtrahend;
+            (intensity > 0.0).then_some((mass, n64(intensity)))
+        })
+        .collect();
+    output
+        .annotations
+        .retain(|mass, _| output.peaks.contains_key(mass));
+    output.deviations.clear();
+    output.warnings.clear();
+    output.metadata.insert("Background", &background.name);
+    output.metadata.insert("Background factor", factor);
+    Ok(output)
+}
+
+/// Consensus of replicate spectra
+///
+/// Peaks of all the replicates within the tolerance (m/z) from the lightest
+/// one are aligned at their mean mass. Intensities are normalized to the base
+/// peak of every replicate (`100`) and averaged, a peak missing from a
+/// replicate counting as zero. Replicates without intensities are skipped.
+/// Standard deviations go to [`Parsed::deviations`].
+pub fn average(spectra: &[&Parsed], tolerance: f64) -> Result<Parsed> {
+    let spectra = spectra
+        .iter()
+        .filter_map(|parsed| {
+            let max = parsed.peaks.values().max()?.raw();
+            (max > 0.0).then_some((parsed, max))
+        })
+        .collect::<Vec<_>>();
+    if spectra.is_empty() {
+        bail!("no spectra to average");
+    }
+    let mut peaks = Vec::new();
+    for (replicate, (parsed, max)) in spectra.iter().enumerate() {
+        for (mass, intensity) in &parsed.peaks {
+            peaks.push((mass.raw(), replicate, 100.0 * intensity.raw() / max));
+        }
+    }
+    peaks.sort_by(|left, right| left.0.total_cmp(&right.0));
+    let mut output = Parsed {
+        name: format!("Average of {} spectra", spectra.len()),
+        ..Parsed::default()
+    };
+    let mut start = 0;
+    while start < peaks.len() {
+        let end = peaks[start..]
+            .iter()
+            .position(|&(mass, ..)| mass - peaks[start].0 > tolerance)
+            .map_or(peaks.len(), |length| start + length);
+        let aligned = &peaks[start..end];
+        let mass = aligned.iter().map(|&(mass, ..)| mass).sum::<f64>() / aligned.len() as f64;
+        let mut intensities = vec![0.0; spectra.len()];
+        for &(_, replicate, intensity) in aligned {
+            intensities[replicate] += intensity;
+        }
+        let summary = Summary::new(&intensities);
+        output.peaks.insert(n64(mass), n64(summary.mean));
+        output.deviations.insert(n64(mass), n64(summary.std_dev));
+        start = end;
+    }
+    output.metadata.insert(
+        "Replicates",
+        spectra.iter().map(|(parsed, _)| &parsed.name).join("; "),
+    );
+    Ok(output)
+}
+
+/// Scale of the background
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scale {
+    /// Intensities times the factor
+    Factor(f64),
+    /// Intensities scaled so the reference ion (mass) matches the sample
+    Ion(N64),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn factor() {
+        let sample = parse("Name: Sample\nNum Peaks: 3\n41 100; 43 50; 57 10;\n").unwrap();
+        let background = parse("Name: Blank\nNum Peaks: 2\n43 40; 57 20;\n").unwrap();
+        let output = subtract(&sample, &background, Scale::Factor(0.5)).unwrap();
+        assert_eq!(output.name, "Sample - Blank");
+        assert_eq!(
+            output.peaks.into_iter().collect::<Vec<_>>(),
+            [(n64(41.0), n64(100.0)), (n64(43.0), n64(30.0))],
+        );
+        assert_eq!(output.metadata.get("Background"), Some("Blank"));
+    }
+
+    #[test]
+    fn average() {
+        let first = parse("Name: A\nNum Peaks: 2\n41 50; 43 100;\n").unwrap();
+        let second = parse("Name: B\nNum Peaks: 3\n41.1 100; 43 200; 57 20;\n").unwrap();
+        let output = super::average(&[&first, &second], 0.5).unwrap();
+        assert_eq!(
+            output
+                .peaks
+                .keys()
+                .map(|mass| mass.raw())
+                .collect::<Vec<_>>(),
+            [41.05, 43.0, 57.0],
+        );
+        assert_eq!(output.peaks[&n64(43.0)], 100.0);
+        assert_eq!(output.deviations[&n64(43.0)], 0.0);
+        assert_eq!(output.peaks[&n64(57.0)], 5.0);
+        assert!((output.deviations[&n64(57.0)].raw() - 50f64.sqrt()).abs() < 1e-9);
+        assert_eq!(output.metadata.get("Replicates"), Some("A; B"));
+        assert!(super::average(&[], 0.5).is_err());
+    }
+
+    #[test]
+    fn empty() {
+        let first = parse("Name: A\nNum Peaks: 2\n41 50; 43 100;\n").unwrap();
+        let second = parse("Name: B\nNum Peaks: 2\n41 0; 43 0;\n").unwrap();
+        let output = super::average(&[&first, &second], 0.5).unwrap();
+        assert_eq!(output.name, "Average of 1 spectra");
+        assert_eq!(output.peaks[&n64(41.0)], 50.0);
+        assert_eq!(output.deviations[&n64(41.0)], 0.0);
+        assert_eq!(output.metadata.get("Replicates"), Some("A"));
+        assert!(super::average(&[&second], 0.5).is_err());
+    }
+
+    #[test]
+    fn ion() {
+        let sample = parse("Name: Sample\nNum Peaks: 2\n41 100; 43 50;\n").unwrap();
+        let background = parse("Name: Blank\nNum Peaks: 2\n41 10; 43 25;\n").unwrap();
+        let output = subtract(&sample, &background, Scale::Ion(n64(43.0))).unwrap();
+        assert_eq!(
+            output.peaks.into_iter().collect::<Vec<_>>(),
+            [(n64(41.0), n64(80.0))],
+        );
+        assert!(subtract(&sample, &background, Scale::Ion(n64(57.0))).is_err());
+        let background = parse("Name: Blank\nNum Peaks: 2\n41 10; 43 0;\n").unwrap();
+        assert!(subtract(&sample, &background, Scale::Ion(n64(43.0))).is_err());
+    }
+
+    #[test]
+    fn deviations() {
+        let first = parse("Name: A\nNum Peaks: 2\n41 50; 43 100;\n").unwrap();
+        let second = parse("Name: B\nNum Peaks: 2\n41 100; 43 100;\n").unwrap();
+        let sample = super::average(&[&first, &second], 0.5).unwrap();
+        assert!(!sample.deviations.is_empty());
+        let output = subtract(&sample, &first, Scale::Factor(0.5)).unwrap();
+        assert!(output.deviations.is_empty());
+    }
+}
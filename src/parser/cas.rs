@@ -0,0 +1,124 @@
+use anyhow::{bail, Error, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{self, Display, Formatter},
+    ops::Range,
+    str::FromStr,
+};
+
+/// CAS registry numbers (2 to 7 digits in the first group)
+const RANGE: Range<u64> = 10_000..10_000_000_000;
+
+/// CAS registry number
+///
+/// Accepts both the hyphenated (`50-00-0`) and the bare (`50000`) form and
+/// displays the hyphenated one. The check digit isn't enforced on parsing, see
+/// [`Cas::is_valid`].
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct Cas(u64);
+
+impl Cas {
+    pub fn new(value: u64) -> Result<Self> {
+        if !RANGE.contains(&value) {
+            bail!("CAS registry number {value} isn't 5 to 10 digits long");
+        }
+        Ok(Self(value))
+    }
+
+    pub fn value(&self) -> u64 {
+        self.0
+    }
+
+    /// Check digit (the last one)
+    pub fn check_digit(&self) -> u64 {
+        self.0 % 10
+    }
+
+    /// Check digit computed from the other digits
+    pub fn checksum(&self) -> u64 {
+        let mut digits = self.0 / 10;
+        let mut position = 1;
+        let mut sum = 0;
+        while digits != 0 {
+            sum += digits % 10 * position;
+            digits /= 10;
+            position += 1;
+        }
+        sum % 10
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.check_digit() == self.checksum()
+    }
+}
+
+impl Display for Cas {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}-{:02}-{}",
+            self.0 / 1000,
+            self.0 / 10 % 100,
+            self.0 % 10
+        )
+    }
+}
+
+impl FromStr for Cas {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let parts = value.split('-').collect::<Vec<_>>();
+        let valid = match &*parts {
+            [bare] => !bare.is_empty(),
+            [first, second, third] => {
+                (2..=7).contains(&first.len()) && second.len() == 2 && third.len() == 1
+            }
+            _ => false,
+        };
+        if !valid
+            || !parts
+                .iter()
+                .all(|part| part.bytes().all(|byte| byte.is_ascii_digit()))
+        {
+            bail!("invalid CAS registry number {value:?}");
+        }
+        Self::new(parts.concat().parse()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!("50-00-0".parse::<Cas>().unwrap(), Cas::new(50000).unwrap());
+        assert_eq!("50000".parse::<Cas>().unwrap(), Cas::new(50000).unwrap());
+        assert_eq!("7732-18-5".parse::<Cas>().unwrap().to_string(), "7732-18-5");
+        assert!("7732-185".parse::<Cas>().is_err());
+        assert!("7732-1-85".parse::<Cas>().is_err());
+        assert!("-18-5".parse::<Cas>().is_err());
+        assert!("".parse::<Cas>().is_err());
+        assert!("123".parse::<Cas>().is_err());
+        assert!("00-12-3".parse::<Cas>().is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        assert!(Cas::new(123).is_err());
+        assert!(Cas::new(10_000_000_000).is_err());
+        for value in [10_000, 50_000, 9_999_999_999] {
+            let cas = Cas::new(value).unwrap();
+            assert_eq!(cas.to_string().parse::<Cas>().unwrap(), cas);
+        }
+    }
+
+    #[test]
+    fn check_digit() {
+        assert!(Cas::new(50000).unwrap().is_valid());
+        assert!(Cas::new(7732185).unwrap().is_valid());
+        assert!(!Cas::new(7732186).unwrap().is_valid());
+        assert_eq!(Cas::new(7732186).unwrap().checksum(), 5);
+    }
+}
@@ -0,0 +1,117 @@
+use nom::error::{VerboseError, VerboseErrorKind};
+use std::{
+    error,
+    fmt::{self, Display, Formatter},
+    ops::Range,
+};
+
+/// Parse error
+#[derive(Clone, Debug, PartialEq)]
+pub struct Error {
+    /// Index of the record in the library
+    pub record: usize,
+    /// Line number (1-based)
+    pub line: usize,
+    /// Column number (1-based, in characters)
+    pub column: usize,
+    /// What the parser expected
+    pub expected: String,
+    /// Offending line
+    pub text: String,
+    /// Offending span (byte range in `text`)
+    pub span: Range<usize>,
+}
+
+impl Error {
+    pub(super) fn new(input: &str, error: nom::Err<VerboseError<&str>>) -> Self {
+        let (remaining, expected) = match &error {
+            nom::Err::Error(error) | nom::Err::Failure(error) => (
+                error.errors.first().map_or("", |(remaining, _)| remaining),
+                expected(error),
+            ),
+            nom::Err::Incomplete(_) => ("", "more input".to_owned()),
+        };
+        let offset = input.len() - remaining.len();
+        let start = input[..offset].rfind('\n').map_or(0, |index| index + 1);
+        let end = input[offset..]
+            .find('\n')
+            .map_or(input.len(), |index| offset + index);
+        let text = input[start..end].trim_end_matches('\r');
+        let span = (offset - start).min(text.len());
+        let length = text[span..]
+            .find(|char: char| char.is_whitespace() || ",;".contains(char))
+            .unwrap_or(text.len() - span);
+        Self {
+            record: 0,
+            line: input[..offset].matches('\n').count() + 1,
+            column: input[start..offset].chars().count() + 1,
+            expected,
+            text: text.to_owned(),
+            span: span..span + length,
+        }
+    }
+
+    /// Moves the error to the record of a library starting at the line
+    pub(super) fn offset(mut self, record: usize, line: usize) -> Self {
+        self.record = record;
+        self.line += line;
+        self
+    }
+
+    /// Moves the error back to the input before cleaning, given the line and
+    /// indentation of every cleaned line
+    pub(super) fn remap(mut self, lines: &[(usize, usize)]) -> Self {
+        match lines.get(self.line - 1) {
+            Some(&(line, indentation)) => {
+                self.line = line + 1;
+                self.column += indentation;
+            }
+            None => {
+                if let Some(&(line, _)) = lines.last() {
+                    self.line = line + 2;
+                }
+            }
+        }
+        self
+    }
+
+    /// Offending text
+    pub fn found(&self) -> &str {
+        &self.text[self.span.clone()]
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "record {}, line {}, column {}: expected {}, ",
+            self.record, self.line, self.column, self.expected,
+        )?;
+        match self.found() {
+            "" => f.write_str("found end of line"),
+            found => write!(f, "found {found:?}"),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+/// The innermost expected char, otherwise the innermost context
+fn expected(error: &VerboseError<&str>) -> String {
+    if let Some((_, VerboseErrorKind::Char(char))) = error.errors.first() {
+        return format!("{char:?}");
+    }
+    error
+        .errors
+        .iter()
+        .find_map(|(_, kind)| match kind {
+            VerboseErrorKind::Context(context) => Some(context.to_string()),
+            _ => None,
+        })
+        .or_else(|| match error.errors.first() {
+            Some((_, VerboseErrorKind::Nom(kind))) => Some(kind.description().to_lowercase()),
+            _ => None,
+        })
+        .unwrap_or_else(|| "valid input".to_owned())
+}
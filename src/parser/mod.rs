@@ -0,0 +1,717 @@
+pub use self::{cas::Cas, error::Error, name::Model, warning::Warning};
+
+use crate::utils::FloatExt;
+use noisy_float::types::N64;
+use nom::{
+    branch::alt,
+    bytes::complete::{escaped_transform, is_not, tag_no_case},
+    character::complete::{char, digit1, line_ending, not_line_ending, one_of, space0},
+    combinator::{cut, eof, map, map_opt, map_res, opt, recognize, success, value, verify},
+    error::{context, VerboseError},
+    multi::{length_count, many0, many1, separated_list0},
+    number::complete::double,
+    sequence::{delimited, pair, preceded, separated_pair, terminated, tuple},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    default::default,
+    str::{self, FromStr},
+};
+use tracing::trace;
+use uom::si::f64::Time;
+
+type IResult<'a, T> = nom::IResult<&'a str, T, VerboseError<&'a str>>;
+
+/// Parses every record of a MSP library
+///
+/// Records are separated by blank lines. Each record is parsed independently,
+/// so a malformed record is reported without discarding the others.
+pub fn parse_library(input: &str) -> Vec<Result<Parsed, Error>> {
+    parse_library_with(input, Mode::Strict)
+}
+
+/// Parses every record of a MSP library in the mode
+///
+/// In [`Mode::Lenient`] a blank line ends a record only if a `Name` line
+/// follows it.
+pub fn parse_library_with(input: &str, mode: Mode) -> Vec<Result<Parsed, Error>> {
+    records(input, mode)
+        .enumerate()
+        .map(|(index, (offset, record))| match parse_with(record, mode) {
+            Ok(mut parsed) => {
+                for warning in &mut parsed.warnings {
+                    warning.offset(offset);
+                }
+                Ok(parsed)
+            }
+            Err(error) => Err(error.offset(index, offset)),
+        })
+        .collect()
+}
+
+pub fn parse(input: &str) -> Result<Parsed, Error> {
+    parse_with(input, Mode::Strict)
+}
+
+/// Parses a record in the mode
+pub fn parse_with(input: &str, mode: Mode) -> Result<Parsed, Error> {
+    match mode {
+        Mode::Strict => record(input, mode),
+        Mode::Lenient => {
+            let (cleaned, lines, mut warnings) = clean(input);
+            let mut parsed = record(&cleaned, mode).map_err(|error| error.remap(&lines))?;
+            warnings.append(&mut parsed.warnings);
+            parsed.warnings = warnings;
+            Ok(parsed)
+        }
+    }
+}
+
+/// Parsing mode
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub enum Mode {
+    /// Anything off the format is an error
+    #[default]
+    Strict,
+    /// Repairs what it can, recording a [`Warning`] for every repair
+    Lenient,
+}
+
+fn record(input: &str, mode: Mode) -> Result<Parsed, Error> {
+    let mut output: Parsed = default();
+    // NIST number on the CAS line, unless there is a separate one
+    let mut cas_nist = None;
+    // Repairs of the peaks (lenient mode)
+    let mut repairs = Vec::new();
+    terminated(
+        tuple((
+            context(
+                "Name",
+                map(line(tag_no_case("Name"), not_line_ending), |name| {
+                    trace!(%name);
+                    output.name = name.to_owned()
+                }),
+            ),
+            many0(alt((
+                map(
+                    line(tag_no_case("CAS#"), pair(cas, opt(nist))),
+                    |(cas, nist)| {
+                        trace!(%cas, ?nist);
+                        if !cas.is_valid() {
+                            output.warnings.push(Warning::Cas(cas));
+                        }
+                        output.cas = Some(cas);
+                        cas_nist = nist;
+                    },
+                ),
+                map(line(tag_no_case("DB#"), number), |db| {
+                    trace!(%db);
+                    output.db = Some(db)
+                }),
+                map(line(tag_no_case("MW"), number), |mw| {
+                    trace!(%mw);
+                    output.mw = Some(mw)
+                }),
+                map(line(tag_no_case("NIST#"), number), |nist| {
+                    trace!(%nist);
+                    output.nist = Some(nist)
+                }),
+                map(line(tag_no_case("Deviations"), deviations), |deviations| {
+                    trace!(?deviations);
+                    output.deviations.extend(deviations)
+                }),
+                // The first Comments, Formula and Synonym, the other fields and
+                // their repeats to the metadata
+                map(field, |(key, value)| {
+                    trace!(%key, %value);
+                    let dedicated = match &*key.to_ascii_lowercase() {
+                        "comments" => Some(&mut output.comments),
+                        "formula" => Some(&mut output.formula),
+                        "synonym" => Some(&mut output.synonym),
+                        _ => None,
+                    };
+                    match dedicated {
+                        Some(dedicated) if dedicated.is_empty() => *dedicated = value.to_owned(),
+                        _ => output.metadata.insert(key, value),
+                    }
+                }),
+            ))),
+            map(peaks(mode), |(peaks, warnings)| {
+                trace!(?peaks, ?warnings);
+                repairs = warnings;
+                for (mass, intensity, annotation) in peaks {
+                    output.peaks.insert(mass, intensity);
+                    if let Some(annotation) = annotation {
+                        output.annotations.insert(mass, annotation);
+                    }
+                }
+            }),
+        )),
+        context("end of record", eof),
+    )(input)
+    .map_err(|error| Error::new(input, error))?;
+    output.nist = output.nist.or(cas_nist);
+    if let Some(name) = name::chemstation(&output.name) {
+        trace!(?name);
+        output.scan = Some(name.scan);
+        output.retention_time = Some(name.retention_time);
+        output.model = name.model;
+        output.source = name.source.map(ToOwned::to_owned);
+    }
+    output.warnings.append(&mut repairs);
+    Ok(output)
+}
+
+/// Drops blank and comment (`#`) lines and strips indentation
+///
+/// Returns the cleaned input, the line and indentation (in characters) of
+/// every kept line in the original input, and a warning for every repair.
+fn clean(input: &str) -> (String, Vec<(usize, usize)>, Vec<Warning>) {
+    let mut output = String::with_capacity(input.len());
+    let mut lines = Vec::new();
+    let mut warnings = Vec::new();
+    for (index, line) in input.split_inclusive('\n').enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() {
+            warnings.push(Warning::Blank(index + 1));
+            continue;
+        }
+        if trimmed.starts_with('#') {
+            warnings.push(Warning::Comment(index + 1));
+            continue;
+        }
+        let indentation = line[..line.len() - trimmed.len()].chars().count();
+        if indentation != 0 {
+            warnings.push(Warning::Indentation(index + 1));
+        }
+        output.push_str(trimmed);
+        lines.push((index, indentation));
+    }
+    (output, lines, warnings)
+}
+
+/// Splits a library into records
+///
+/// Each record is a slice of the input from its first non-blank line up to
+/// (but not including) the next blank line, paired with the index of its
+/// first line.
+fn records(input: &str, mode: Mode) -> impl Iterator<Item = (usize, &str)> {
+    let mut records = Vec::new();
+    let mut start = None;
+    // Offset of the first blank line and the line after the blank lines
+    // (lenient mode)
+    let mut blank = None;
+    let mut next = None;
+    let mut offset = 0;
+    for (index, line) in input.split_inclusive('\n').enumerate() {
+        let trimmed = line.trim();
+        match (trimmed.is_empty(), start) {
+            (false, None) => start = Some((index, offset)),
+            (true, Some((line, start_offset))) => match mode {
+                Mode::Strict => {
+                    records.push((line, &input[start_offset..offset]));
+                    start = None;
+                }
+                Mode::Lenient => {
+                    blank.get_or_insert(offset);
+                    next = None;
+                }
+            },
+            (false, Some((line, start_offset))) => {
+                if let Some(end) = blank {
+                    let next = *next.get_or_insert((index, offset));
+                    if is_name(trimmed) {
+                        records.push((line, &input[start_offset..end]));
+                        start = Some(next);
+                        blank = None;
+                    } else if !trimmed.starts_with('#') {
+                        blank = None;
+                    }
+                }
+            }
+            _ => {}
+        }
+        offset += line.len();
+    }
+    if let Some((line, start_offset)) = start {
+        records.push((line, &input[start_offset..blank.unwrap_or(input.len())]));
+    }
+    records.into_iter()
+}
+
+fn is_name(line: &str) -> bool {
+    line.get(..4)
+        .is_some_and(|name| name.eq_ignore_ascii_case("Name"))
+        && line[4..].trim_start().starts_with(':')
+}
+
+fn line<'a, T>(
+    tag: impl Fn(&'a str) -> IResult<'a, &'a str>,
+    value: impl FnMut(&'a str) -> IResult<'a, T>,
+) -> impl FnMut(&'a str) -> IResult<'a, T> {
+    delimited(
+        terminated(tag, char(':')),
+        delimited(space0, value, space0),
+        line_ending,
+    )
+}
+
+/// CAS registry number
+fn cas(input: &str) -> IResult<Cas> {
+    map_res(
+        recognize(pair(
+            digit1,
+            opt(tuple((char('-'), digit1, char('-'), digit1))),
+        )),
+        str::parse,
+    )(input)
+}
+
+/// NIST number on the CAS line (`CAS#: 50-00-0; NIST#: 1234`)
+fn nist(input: &str) -> IResult<u64> {
+    preceded(
+        tuple((char(';'), space0, tag_no_case("NIST#"), char(':'), space0)),
+        number,
+    )(input)
+}
+
+/// Standard deviations of the intensities (`41 1.5; 43 0.2`)
+fn deviations(input: &str) -> IResult<Vec<(N64, N64)>> {
+    separated_list0(
+        pair(char(';'), space0),
+        separated_pair(float, char(' '), float),
+    )(input)
+}
+
+/// Any other `key: value` line
+fn field(input: &str) -> IResult<(&str, &str)> {
+    terminated(
+        separated_pair(
+            map(
+                verify(is_not(":\r\n"), |key: &str| {
+                    !key.trim().eq_ignore_ascii_case("Num Peaks")
+                }),
+                str::trim,
+            ),
+            char(':'),
+            map(not_line_ending, str::trim),
+        ),
+        line_ending,
+    )(input)
+}
+
+/// Mass, intensity and annotation of a peak
+type Peak = (N64, N64, Option<String>);
+
+/// Peaks and the repairs made to read them
+///
+/// In [`Mode::Lenient`] the number of peaks may differ from `Num Peaks` and
+/// the separator after the last peak may be missing.
+fn peaks<'a>(mode: Mode) -> impl FnMut(&'a str) -> IResult<'a, (Vec<Peak>, Vec<Warning>)> {
+    move |input| match mode {
+        Mode::Strict => map(
+            length_count(
+                num_peaks,
+                terminated(peak, context("separator", multiseparator)),
+            ),
+            |peaks| (peaks, Vec::new()),
+        )(input),
+        Mode::Lenient => {
+            let (input, (expected, peaks)) =
+                pair(num_peaks, many0(pair(peak, opt(multiseparator))))(input)?;
+            let mut warnings = Vec::new();
+            for ((mass, ..), separator) in &peaks {
+                if separator.is_none() {
+                    warnings.push(Warning::Separator(*mass));
+                }
+            }
+            if expected != peaks.len() as u64 {
+                warnings.push(Warning::NumPeaks {
+                    expected,
+                    found: peaks.len(),
+                });
+            }
+            let peaks = peaks.into_iter().map(|(peak, _)| peak).collect();
+            Ok((input, (peaks, warnings)))
+        }
+    }
+}
+
+fn num_peaks(input: &str) -> IResult<u64> {
+    context("Num Peaks", line(tag_no_case("Num Peaks"), number))(input)
+}
+
+fn peak(input: &str) -> IResult<Peak> {
+    tuple((
+        context("mass", float),
+        preceded(
+            context("separator", multiseparator),
+            context("intensity", float),
+        ),
+        opt(preceded(space0, annotation)),
+    ))(input)
+}
+
+/// Quoted peak annotation (`91 999 "C7H7+"`), with `\"` and `\\` escaped
+fn annotation(input: &str) -> IResult<String> {
+    preceded(
+        char('"'),
+        cut(terminated(
+            map(
+                opt(escaped_transform(
+                    is_not("\\\"\r\n"),
+                    '\\',
+                    // Any other backslash is literal
+                    alt((
+                        value("\\", char('\\')),
+                        value("\"", char('"')),
+                        value("\\", success(())),
+                    )),
+                )),
+                Option::unwrap_or_default,
+            ),
+            char('"'),
+        )),
+    )(input)
+}
+
+fn multiseparator(input: &str) -> IResult<Vec<&str>> {
+    many1(alt((recognize(one_of(" \t,;:()[]{}")), line_ending)))(input)
+}
+
+fn number<T: FromStr>(input: &str) -> IResult<T> {
+    map_res(digit1, str::parse)(input)
+}
+
+/// Finite and non-negative float
+fn float(input: &str) -> IResult<N64> {
+    map_opt(double, |value| {
+        (value.is_finite() && value >= 0.0).then(|| N64::new(value))
+    })(input)
+}
+
+/// Peaks (mass to intensity)
+pub type Peaks = BTreeMap<N64, N64>;
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Parsed {
+    pub name: String,
+    pub cas: Option<Cas>,
+    pub comments: String,
+    pub db: Option<u64>,
+    pub formula: String,
+    pub mw: Option<u64>,
+    pub nist: Option<u64>,
+    pub synonym: String,
+    /// Scan number (ChemStation name)
+    pub scan: Option<u64>,
+    /// Retention time (ChemStation name)
+    pub retention_time: Option<Time>,
+    /// Deconvolution model (ChemStation name)
+    pub model: Option<Model>,
+    /// Source data file (ChemStation name)
+    pub source: Option<String>,
+    pub metadata: Metadata,
+    pub peaks: Peaks,
+    /// Peak annotations (mass to text)
+    pub annotations: BTreeMap<N64, String>,
+    /// Standard deviations of the intensities (mass to deviation, consensus
+    /// spectra), read from the `Deviations` line
+    pub deviations: BTreeMap<N64, N64>,
+    pub warnings: Vec<Warning>,
+}
+
+impl Parsed {
+    /// Intensities binned by nominal mass
+    pub fn intensities(&self) -> Vec<f64> {
+        let mut intensities = Vec::new();
+        for (mass, intensity) in &self.peaks {
+            let mass = mass.raw().round() as usize;
+            if mass >= intensities.len() {
+                intensities.resize(mass + 1, 0.0);
+            }
+            intensities[mass] += intensity.raw();
+        }
+        intensities
+    }
+
+    /// All masses are integers (nominal mass spectrum)
+    pub fn is_nominal(&self) -> bool {
+        self.peaks.keys().all(|mass| mass.raw().is_approx_integer())
+    }
+}
+
+/// Metadata
+///
+/// Fields without a dedicated [`Parsed`] member (or repeating one), in the
+/// order they were read. Keys are compared case-insensitively and may repeat.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Metadata(Vec<(String, String)>);
+
+impl Metadata {
+    pub fn insert(&mut self, key: impl ToString, value: impl ToString) {
+        self.0.push((key.to_string(), value.to_string()));
+    }
+
+    /// Replaces the values of the key by the value, in place of the first one
+    pub fn replace(&mut self, key: impl ToString, value: impl ToString) {
+        let key = key.to_string();
+        let Some(position) = self
+            .0
+            .iter()
+            .position(|(other, _)| other.eq_ignore_ascii_case(&key))
+        else {
+            return self.insert(key, value);
+        };
+        self.0[position].1 = value.to_string();
+        let mut rest = self.0.split_off(position + 1);
+        rest.retain(|(other, _)| !other.eq_ignore_ascii_case(&key));
+        self.0.append(&mut rest);
+    }
+
+    /// First value of the key
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(other, _)| other.eq_ignore_ascii_case(key))
+            .map(|(_, value)| &**value)
+    }
+
+    /// All values of the key
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> {
+        self.0
+            .iter()
+            .filter(move |(other, _)| other.eq_ignore_ascii_case(key))
+            .map(|(_, value)| &**value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(key, value)| (&**key, &**value))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+mod cas;
+mod error;
+mod name;
+mod warning;
+
+impl FromStr for Parsed {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        parse(value)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use noisy_float::types::n64;
+    use std::{
+        fs::{read_dir, read_to_string},
+        path::{Path, PathBuf},
+    };
+
+    const RECORD: &str = "Name: First\r\nDB#: 1\r\nNum Peaks: 2\r\n27 28; 29 39; \r\n";
+
+    #[test]
+    fn library() {
+        let input = format!("{RECORD}\r\n\r\nName: Second\nNum Peaks: 1\n41 999;\n\n{RECORD}");
+        let parsed = parse_library(&input);
+        assert_eq!(parsed.len(), 3);
+        assert_eq!(parsed[0].as_ref().unwrap().name, "First");
+        assert_eq!(parsed[1].as_ref().unwrap().name, "Second");
+        assert_eq!(parsed[2].as_ref().unwrap().peaks.len(), 2);
+    }
+
+    #[test]
+    fn library_with_error() {
+        let input = format!("{RECORD}\nName: Broken\nNum Peaks: 2\n41 99; abc 1\n\n{RECORD}");
+        let parsed = parse_library(&input);
+        assert_eq!(parsed.len(), 3);
+        assert!(parsed[0].is_ok());
+        assert!(parsed[2].is_ok());
+        let error = parsed[1].as_ref().unwrap_err();
+        assert_eq!(error.record, 1);
+        assert_eq!(error.line, 8);
+        assert_eq!(error.column, 8);
+        assert_eq!(error.expected, "mass");
+        assert_eq!(error.text, "41 99; abc 1");
+        assert_eq!(error.found(), "abc");
+    }
+
+    #[test]
+    fn error() {
+        let error = parse("Name: X\nNum Peaks: 1\n41 999;\n42 1;\n").unwrap_err();
+        assert_eq!((error.line, error.column), (4, 1));
+        assert_eq!(error.expected, "end of record");
+        let error = parse("Name: X\n41 999;\n").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 1));
+        assert_eq!(error.expected, "Num Peaks");
+        let error = parse("Name: X\nNum Peaks: 1\n41 999").unwrap_err();
+        assert_eq!((error.line, error.column), (3, 7));
+        assert_eq!(error.expected, "separator");
+        assert_eq!(error.found(), "");
+    }
+
+    #[test]
+    fn metadata() {
+        let input =
+            "NAME: Unknown\nPrecursorMZ: 303.2324\nPrecursor_type: [M+H]+\nComment: first\n\
+                     comment: second\nNum Peaks: 1\n41 999\n";
+        let parsed = parse(input).unwrap();
+        assert_eq!(parsed.name, "Unknown");
+        assert_eq!(parsed.metadata.get("precursormz"), Some("303.2324"));
+        assert_eq!(parsed.metadata.get("PRECURSOR_TYPE"), Some("[M+H]+"));
+        assert_eq!(
+            parsed.metadata.get_all("Comment").collect::<Vec<_>>(),
+            ["first", "second"],
+        );
+        assert_eq!(parsed.peaks.len(), 1);
+    }
+
+    #[test]
+    fn repeated() {
+        let input = "Name: Ethanol\nSynonym: Ethyl alcohol\nFormula: C2H6O\nSynonym: \
+                     Alcohol\nComments: first\nComments: second\nNum Peaks: 1\n31 999\n";
+        let parsed = parse(input).unwrap();
+        assert_eq!(parsed.synonym, "Ethyl alcohol");
+        assert_eq!(parsed.formula, "C2H6O");
+        assert_eq!(parsed.comments, "first");
+        assert_eq!(
+            parsed.metadata.iter().collect::<Vec<_>>(),
+            [("Synonym", "Alcohol"), ("Comments", "second")],
+        );
+    }
+
+    #[test]
+    fn cas() {
+        let parsed =
+            parse("Name: Formaldehyde\nCAS#: 50-00-0; NIST#: 12345\nNum Peaks: 0\n").unwrap();
+        assert_eq!(parsed.cas, Some(Cas::new(50000).unwrap()));
+        assert_eq!(parsed.nist, Some(12345));
+        assert!(parsed.warnings.is_empty());
+        let parsed = parse("Name: Water\nCAS#: 7732186\nNum Peaks: 0\n").unwrap();
+        assert_eq!(parsed.cas, Some(Cas::new(7732186).unwrap()));
+        assert_eq!(parsed.warnings, [Warning::Cas(Cas::new(7732186).unwrap())]);
+    }
+
+    #[test]
+    fn annotations() {
+        let input =
+            "Name: Toluene\nNum Peaks: 3\n65 100\n91 999 \"C7H7+\"\n92 620\t\"p-15/0.3ppm\"; \n";
+        let parsed = parse(input).unwrap();
+        assert_eq!(parsed.peaks.len(), 3);
+        assert_eq!(
+            parsed.annotations.into_iter().collect::<Vec<_>>(),
+            [
+                (n64(91.0), "C7H7+".to_owned()),
+                (n64(92.0), "p-15/0.3ppm".to_owned()),
+            ],
+        );
+        let error = parse("Name: X\nNum Peaks: 1\n91 999 \"C7H7+\n").unwrap_err();
+        assert_eq!((error.line, error.expected.as_str()), (3, "'\"'"));
+        let parsed =
+            parse("Name: X\nNum Peaks: 2\n91 999 \"a \\\"b\\\" \\\\ c\\d\"; 92 1 \"\"\n").unwrap();
+        assert_eq!(parsed.annotations[&n64(91.0)], "a \"b\" \\ c\\d");
+        assert_eq!(parsed.annotations[&n64(92.0)], "");
+    }
+
+    #[test]
+    fn fractional() {
+        let input = "Name: Orbitrap\nNum Peaks: 3\n121.0648 2345.7; 122 12\n149.02332 0.5\n";
+        let parsed = parse(input).unwrap();
+        assert_eq!(
+            parsed.peaks.into_iter().collect::<Vec<_>>(),
+            [
+                (n64(121.0648), n64(2345.7)),
+                (n64(122.0), n64(12.0)),
+                (n64(149.02332), n64(0.5)),
+            ],
+        );
+    }
+
+    #[test]
+    fn invalid_floats() {
+        for peak in ["41 inf", "inf 10", "41 NaN", "-41 10", "41 -10"] {
+            let error = parse(&format!("Name: X\nNum Peaks: 1\n{peak};\n")).unwrap_err();
+            assert_eq!(error.line, 3, "{peak}");
+        }
+    }
+
+    #[test]
+    fn lenient() {
+        let input = "  Name: X\n# exported\nNum Peaks: 3\n41 50; 43 200\n\n44 10";
+        assert!(parse(input).is_err());
+        let parsed = parse_with(input, Mode::Lenient).unwrap();
+        assert_eq!(parsed.peaks.len(), 3);
+        assert_eq!(
+            parsed.warnings,
+            [
+                Warning::Indentation(1),
+                Warning::Comment(2),
+                Warning::Blank(5),
+                Warning::Separator(n64(44.0)),
+            ],
+        );
+        let parsed = parse_with("Name: X\nNum Peaks: 3\n41 50; 43 200;\n", Mode::Lenient).unwrap();
+        assert_eq!(
+            parsed.warnings,
+            [Warning::NumPeaks {
+                expected: 3,
+                found: 2,
+            }],
+        );
+        let error = parse_with("Name: X\n\n  Num Peaks: 1\n  41 abc\n", Mode::Lenient).unwrap_err();
+        assert_eq!((error.line, error.column), (4, 3));
+    }
+
+    #[test]
+    fn lenient_library() {
+        let input = "Name: A\nNum Peaks: 2\n41 50;\n\n43 200;\n\nName: B\nNum Peaks: 1\n41 1;\n";
+        assert_eq!(parse_library(input).len(), 3);
+        let parsed = parse_library_with(input, Mode::Lenient);
+        assert_eq!(parsed.len(), 2);
+        let first = parsed[0].as_ref().unwrap();
+        assert_eq!(first.peaks.len(), 2);
+        assert_eq!(first.warnings, [Warning::Blank(4)]);
+        let second = parsed[1].as_ref().unwrap();
+        assert_eq!(second.name, "B");
+        assert!(second.warnings.is_empty());
+    }
+
+    #[test]
+    fn nominal() {
+        let parsed = parse(RECORD).unwrap();
+        assert!(parsed.is_nominal());
+        assert_eq!(parsed.intensities()[27..], [28.0, 0.0, 39.0]);
+    }
+
+    #[test]
+    fn inputs() {
+        for path in files(&Path::new(env!("CARGO_MANIFEST_DIR")).join("input")) {
+            let input = read_to_string(&path).unwrap();
+            for parsed in parse_library(&input) {
+                assert!(parsed.is_ok(), "{}", path.display());
+            }
+        }
+    }
+
+    pub(crate) fn files(path: &Path) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        for entry in read_dir(path).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                files.extend(self::files(&path));
+            } else {
+                files.push(path);
+            }
+        }
+        files
+    }
+}
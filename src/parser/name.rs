@@ -0,0 +1,139 @@
+use super::{number, IResult};
+use nom::{
+    branch::alt,
+    bytes::complete::{is_not, tag, tag_no_case},
+    character::complete::{char, space0, space1},
+    combinator::{all_consuming, map, opt, rest},
+    number::complete::double,
+    sequence::{delimited, pair, preceded, tuple},
+};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+use uom::si::{
+    f64::Time,
+    time::{minute, second},
+};
+
+/// Fields of a ChemStation name
+///
+/// `Scan 2797 (50.937 min) in C:\...\DATA.MS` or
+/// `Component at scan 2946 (52.313 min) [Model = +92u] in C:\...\DATA.MS`.
+#[derive(Clone, Debug, PartialEq)]
+pub(super) struct Name<'a> {
+    pub(super) scan: u64,
+    pub(super) retention_time: Time,
+    pub(super) model: Option<Model>,
+    pub(super) source: Option<&'a str>,
+}
+
+/// Parses a ChemStation name, `None` for any other naming style
+pub(super) fn chemstation(input: &str) -> Option<Name> {
+    let (_, (scan, retention_time, model, source)) = all_consuming(tuple((
+        preceded(
+            pair(opt(tag_no_case("Component at ")), tag_no_case("Scan ")),
+            number,
+        ),
+        preceded(space1, delimited(char('('), retention_time, char(')'))),
+        opt(preceded(space1, model)),
+        opt(preceded(delimited(space1, tag_no_case("in"), space1), rest)),
+    )))(input.trim())
+    .ok()?;
+    Some(Name {
+        scan,
+        retention_time,
+        model,
+        source,
+    })
+}
+
+fn retention_time(input: &str) -> IResult<Time> {
+    map(
+        pair(
+            double,
+            preceded(space0, alt((tag("min"), tag("sec"), tag("s")))),
+        ),
+        |(value, unit)| match unit {
+            "min" => Time::new::<minute>(value),
+            _ => Time::new::<second>(value),
+        },
+    )(input)
+}
+
+/// `[Model = +92u]`
+fn model(input: &str) -> IResult<Model> {
+    map(
+        delimited(
+            pair(char('['), tag_no_case("Model")),
+            preceded(delimited(space0, char('='), space0), is_not("]")),
+            char(']'),
+        ),
+        |model: &str| model.trim().into(),
+    )(input)
+}
+
+/// Deconvolution model
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum Model {
+    /// Total ion current (`TIC`)
+    Tic,
+    /// Model ion (`+92u`)
+    Ion(u64),
+    Other(String),
+}
+
+impl From<&str> for Model {
+    fn from(value: &str) -> Self {
+        if value.eq_ignore_ascii_case("TIC") {
+            return Self::Tic;
+        }
+        let ion = value.strip_prefix('+').unwrap_or(value);
+        match ion.strip_suffix('u').unwrap_or(ion).parse() {
+            Ok(mass) => Self::Ion(mass),
+            Err(_) => Self::Other(value.to_owned()),
+        }
+    }
+}
+
+impl Display for Model {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Tic => f.write_str("TIC"),
+            Self::Ion(mass) => write!(f, "+{mass}u"),
+            Self::Other(model) => f.write_str(model),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scan() {
+        let name = chemstation(r"Scan 2797 (50.937 min) in C:\USERS\AB-PIC_03.D\DATA.MS").unwrap();
+        assert_eq!(name.scan, 2797);
+        assert_eq!(name.retention_time, Time::new::<minute>(50.937));
+        assert_eq!(name.model, None);
+        assert_eq!(name.source, Some(r"C:\USERS\AB-PIC_03.D\DATA.MS"));
+    }
+
+    #[test]
+    fn component() {
+        let name = chemstation(
+            r"Component at scan 2946 (52.313 min) [Model = +92u] in C:\AB-PIC_03.D\DATA.MS",
+        )
+        .unwrap();
+        assert_eq!(name.scan, 2946);
+        assert_eq!(name.model, Some(Model::Ion(92)));
+        assert_eq!(name.source, Some(r"C:\AB-PIC_03.D\DATA.MS"));
+        let name = chemstation("Component at scan 4926 (28.083 min) [Model = TIC]").unwrap();
+        assert_eq!(name.model, Some(Model::Tic));
+        assert_eq!(name.source, None);
+    }
+
+    #[test]
+    fn other() {
+        assert_eq!(chemstation("Picolinyl 9,12-octadecadienoate"), None);
+        assert_eq!(chemstation("Scan of something"), None);
+    }
+}
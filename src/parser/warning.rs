@@ -0,0 +1,53 @@
+use super::Cas;
+use noisy_float::types::N64;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+
+/// Parse warning
+///
+/// Something suspicious in a record that was read anyway, or a repair made in
+/// lenient mode.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum Warning {
+    /// CAS registry number with a wrong check digit
+    Cas(Cas),
+    /// Skipped blank line (line number)
+    Blank(usize),
+    /// Skipped comment line (line number)
+    Comment(usize),
+    /// Stripped indentation (line number)
+    Indentation(usize),
+    /// `Num Peaks` differs from the number of peaks read
+    NumPeaks { expected: u64, found: usize },
+    /// Missing separator after the peak (mass)
+    Separator(N64),
+}
+
+impl Warning {
+    /// Moves the line number to the record of a library starting at the line
+    pub(super) fn offset(&mut self, line: usize) {
+        if let Self::Blank(number) | Self::Comment(number) | Self::Indentation(number) = self {
+            *number += line;
+        }
+    }
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Cas(cas) => write!(
+                f,
+                "CAS# {cas} has check digit {}, expected {}",
+                cas.check_digit(),
+                cas.checksum(),
+            ),
+            Self::Blank(line) => write!(f, "line {line}: skipped blank line"),
+            Self::Comment(line) => write!(f, "line {line}: skipped comment"),
+            Self::Indentation(line) => write!(f, "line {line}: stripped indentation"),
+            Self::NumPeaks { expected, found } => {
+                write!(f, "Num Peaks is {expected}, found {found} peaks")
+            }
+            Self::Separator(mass) => write!(f, "missing separator after peak {mass}"),
+        }
+    }
+}
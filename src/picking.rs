@@ -0,0 +1,266 @@
+use crate::utils::FloatExt;
+use noisy_float::types::N64;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt::{self, Display, Formatter},
+};
+
+/// Peak picker (the most intense candidates above the threshold)
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Picker {
+    pub method: Method,
+    /// Minimal intensity relative to the base peak (`0..=1`)
+    pub threshold: f64,
+    /// Maximal number of peaks (`0` for all)
+    pub count: usize,
+}
+
+impl Picker {
+    /// Masses of the picked peaks, in increasing order
+    pub fn pick(&self, peaks: &BTreeMap<N64, f64>) -> Vec<N64> {
+        let series = series(peaks);
+        let mut picked = match self.method {
+            Method::All => series
+                .into_iter()
+                .filter(|&(_, intensity)| intensity > 0.0)
+                .collect(),
+            Method::Maximums => maximums(&series),
+            Method::ZScore {
+                lag,
+                threshold,
+                influence,
+            } => {
+                let mut detector = PeaksDetector::new(lag, threshold, influence);
+                series
+                    .into_iter()
+                    .filter(|&(_, intensity)| detector.signal(intensity) == Some(Peak::High))
+                    .collect::<Vec<_>>()
+            }
+        };
+        let base = peaks.values().copied().fold(0.0, f64::max);
+        picked.retain(|&(_, intensity)| intensity >= self.threshold * base);
+        picked.sort_by(|left, right| right.1.total_cmp(&left.1));
+        if self.count != 0 {
+            picked.truncate(self.count);
+        }
+        let mut masses = picked.into_iter().map(|(mass, _)| mass).collect::<Vec<_>>();
+        masses.sort();
+        masses
+    }
+}
+
+impl Default for Picker {
+    fn default() -> Self {
+        Self {
+            method: Method::Maximums,
+            threshold: 0.05,
+            count: 10,
+        }
+    }
+}
+
+/// Peak picking method
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum Method {
+    /// Every peak
+    All,
+    /// Peaks more intense than their neighbours
+    Maximums,
+    /// Peaks deviating from the moving mean of the previous ones by more than
+    /// the threshold (standard deviations)
+    ZScore {
+        lag: usize,
+        threshold: f64,
+        influence: f64,
+    },
+}
+
+impl Display for Method {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::All => f.write_str("All"),
+            Self::Maximums => f.write_str("Maximums"),
+            Self::ZScore { .. } => f.write_str("Z-score"),
+        }
+    }
+}
+
+/// Signal of the peaks detector
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Peak {
+    High,
+    Low,
+}
+
+/// Smoothed z-score peaks detector
+#[derive(Clone, Debug)]
+pub struct PeaksDetector {
+    lag: usize,
+    threshold: f64,
+    influence: f64,
+    window: VecDeque<f64>,
+}
+
+impl PeaksDetector {
+    pub fn new(lag: usize, threshold: f64, influence: f64) -> PeaksDetector {
+        PeaksDetector {
+            lag,
+            threshold,
+            influence,
+            window: VecDeque::with_capacity(lag),
+        }
+    }
+
+    pub fn signal(&mut self, value: f64) -> Option<Peak> {
+        if self.window.len() < self.lag {
+            self.window.push_front(value);
+            return None;
+        }
+        let (mean, std_dev) = self.stats()?;
+        let &previous = self.window.front()?;
+        self.window.pop_back();
+        if (value - mean).abs() > self.threshold * std_dev {
+            self.window
+                .push_front(self.influence * value + (1.0 - self.influence) * previous);
+            Some(if value > mean { Peak::High } else { Peak::Low })
+        } else {
+            self.window.push_front(value);
+            None
+        }
+    }
+
+    /// Mean and (population) standard deviation of the window
+    fn stats(&self) -> Option<(f64, f64)> {
+        if self.window.is_empty() {
+            return None;
+        }
+        let length = self.window.len() as f64;
+        let mean = self.window.iter().sum::<f64>() / length;
+        let variance = self
+            .window
+            .iter()
+            .map(|value| (value - mean).powi(2))
+            .sum::<f64>()
+            / length;
+        Some((mean, variance.sqrt()))
+    }
+}
+
+/// Local maximums of the series: points more intense than the previous one
+/// and at least as intense as the next one (the first point of a plateau)
+pub fn maximums(series: &[(N64, f64)]) -> Vec<(N64, f64)> {
+    let intensity = |index: Option<usize>| {
+        index
+            .and_then(|index| series.get(index))
+            .map_or(0.0, |&(_, intensity)| intensity)
+    };
+    series
+        .iter()
+        .enumerate()
+        .filter(|&(index, &(_, value))| {
+            value > intensity(index.checked_sub(1)) && value >= intensity(Some(index + 1))
+        })
+        .map(|(_, &peak)| peak)
+        .collect()
+}
+
+/// Peaks in the increasing order of the masses, with zeros at the missing
+/// nominal masses in between (nominal spectra)
+fn series(peaks: &BTreeMap<N64, f64>) -> Vec<(N64, f64)> {
+    let nominal = peaks.keys().all(|mass| mass.raw().is_approx_integer());
+    let mut series = Vec::with_capacity(peaks.len());
+    for (&mass, &intensity) in peaks {
+        if nominal {
+            if let Some(&(last, _)) = series.last() {
+                let mut missing = last + 1.0;
+                while missing < mass {
+                    series.push((missing, 0.0));
+                    missing += 1.0;
+                }
+            }
+        }
+        series.push((mass, intensity));
+    }
+    series
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use noisy_float::types::n64;
+
+    fn peaks(peaks: &[(f64, f64)]) -> BTreeMap<N64, f64> {
+        peaks
+            .iter()
+            .map(|&(mass, intensity)| (n64(mass), intensity))
+            .collect()
+    }
+
+    #[test]
+    fn maximums() {
+        let picker = Picker {
+            method: Method::Maximums,
+            threshold: 0.0,
+            count: 0,
+        };
+        let peaks = peaks(&[
+            (41.0, 20.0),
+            (42.0, 10.0),
+            (43.0, 100.0),
+            (44.0, 5.0),
+            (55.0, 30.0),
+            (56.0, 30.0),
+            (57.0, 60.0),
+        ]);
+        assert_eq!(
+            picker.pick(&peaks),
+            [n64(41.0), n64(43.0), n64(55.0), n64(57.0)]
+        );
+    }
+
+    #[test]
+    fn threshold_and_count() {
+        let peaks = peaks(&[(41.0, 20.0), (43.0, 100.0), (55.0, 4.0), (57.0, 60.0)]);
+        let picker = Picker {
+            method: Method::All,
+            threshold: 0.05,
+            count: 0,
+        };
+        assert_eq!(picker.pick(&peaks), [n64(41.0), n64(43.0), n64(57.0)]);
+        let picker = Picker { count: 2, ..picker };
+        assert_eq!(picker.pick(&peaks), [n64(43.0), n64(57.0)]);
+    }
+
+    #[test]
+    fn z_score() {
+        let mut detector = PeaksDetector::new(3, 2.0, 0.0);
+        let signals = [1.0, 1.1, 0.9, 1.0, 5.0, 1.0, -3.0]
+            .into_iter()
+            .map(|value| detector.signal(value))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            signals,
+            [
+                None,
+                None,
+                None,
+                None,
+                Some(Peak::High),
+                None,
+                Some(Peak::Low)
+            ],
+        );
+        let picker = Picker {
+            method: Method::ZScore {
+                lag: 3,
+                threshold: 2.0,
+                influence: 0.0,
+            },
+            threshold: 0.0,
+            count: 0,
+        };
+        let peaks = peaks(&[(40.0, 1.0), (41.0, 1.0), (42.0, 1.0), (44.0, 100.0)]);
+        assert_eq!(picker.pick(&peaks), [n64(44.0)]);
+    }
+}
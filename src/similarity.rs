@@ -0,0 +1,402 @@
+use crate::{parser::Parsed, utils::SortArray};
+use ndarray::{Array2, Axis};
+use noisy_float::types::N64;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+};
+
+/// NIST weights (`m/z³·I^0.6`)
+const NIST: Similarity = Similarity {
+    metric: Metric::WeightedCosine,
+    mass_exponent: 3.0,
+    intensity_exponent: 0.6,
+};
+
+/// Match factor (NIST, `0..=999`)
+///
+/// Squared cosine of the intensities weighted by `m/z³·I^0.6`.
+pub fn match_factor(unknown: &Parsed, library: &Parsed) -> f64 {
+    999.0 * NIST.parsed(unknown, library).powi(2)
+}
+
+/// Reverse match factor (NIST, `0..=999`)
+///
+/// [`match_factor`] ignoring the peaks of the unknown spectrum missing from
+/// the library spectrum, so impurities in the unknown are not penalized.
+pub fn reverse_match_factor(unknown: &Parsed, library: &Parsed) -> f64 {
+    let library = library.intensities();
+    let mut unknown = unknown.intensities();
+    unknown.truncate(library.len());
+    for (unknown, library) in unknown.iter_mut().zip(&library) {
+        if *library == 0.0 {
+            *unknown = 0.0;
+        }
+    }
+    999.0 * NIST.intensities(&unknown, &library).powi(2)
+}
+
+/// Pairwise similarities of the spectra
+pub fn matrix(similarity: &Similarity, spectra: &[&Parsed]) -> Array2<f64> {
+    let intensities = spectra
+        .iter()
+        .map(|parsed| parsed.intensities())
+        .collect::<Vec<_>>();
+    Array2::from_shape_fn((spectra.len(), spectra.len()), |(row, column)| {
+        similarity.intensities(&intensities[row], &intensities[column])
+    })
+}
+
+/// Clusters of a similarity matrix
+///
+/// Spectra at least as similar as the threshold, directly or through other
+/// spectra, share a cluster. Clusters are numbered in order of their first
+/// spectrum.
+pub fn clusters(matrix: &Array2<f64>, threshold: f64) -> Vec<usize> {
+    let mut clusters = vec![usize::MAX; matrix.nrows()];
+    let mut count = 0;
+    for first in 0..matrix.nrows() {
+        if clusters[first] != usize::MAX {
+            continue;
+        }
+        let mut stack = vec![first];
+        clusters[first] = count;
+        while let Some(row) = stack.pop() {
+            for (column, &value) in matrix.row(row).indexed_iter() {
+                if clusters[column] == usize::MAX && value >= threshold {
+                    clusters[column] = count;
+                    stack.push(column);
+                }
+            }
+        }
+        count += 1;
+    }
+    clusters
+}
+
+/// Orders the rows and columns of a similarity matrix by cluster
+///
+/// Returns the order (original indices) and the ordered matrix.
+pub fn order(matrix: Array2<f64>, clusters: &[usize]) -> (Vec<usize>, Array2<f64>) {
+    let compare =
+        |left: &usize, right: &usize| clusters[*left].cmp(&clusters[*right]).then(left.cmp(right));
+    let mut order = (0..clusters.len()).collect::<Vec<_>>();
+    order.sort_by(compare);
+    let matrix = matrix
+        .sort_axis_by(Axis(0), compare)
+        .sort_axis_by(Axis(1), compare);
+    (order, matrix)
+}
+
+/// Spectral similarity
+///
+/// The weights `m/z^mass_exponent·I^intensity_exponent` apply to
+/// [`Metric::WeightedCosine`] and [`Metric::Composite`].
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Similarity {
+    pub metric: Metric,
+    pub mass_exponent: f64,
+    pub intensity_exponent: f64,
+}
+
+impl Similarity {
+    /// Similarity of the intensities indexed by nominal mass
+    pub fn intensities(&self, left: &[f64], right: &[f64]) -> f64 {
+        let intensity = |intensities: &[f64], mass| intensities.get(mass).copied().unwrap_or(0.0);
+        self.aligned(
+            &(0..left.len().max(right.len()))
+                .map(|mass| (mass as f64, intensity(left, mass), intensity(right, mass)))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// Similarity of the spectra binned by nominal mass
+    pub fn parsed(&self, left: &Parsed, right: &Parsed) -> f64 {
+        self.intensities(&left.intensities(), &right.intensities())
+    }
+
+    /// Similarity of the (normalized) peaks binned by nominal mass
+    pub fn normalized(&self, left: &BTreeMap<N64, f64>, right: &BTreeMap<N64, f64>) -> f64 {
+        self.intensities(&binned(left), &binned(right))
+    }
+
+    /// Similarity of the aligned `(mass, left, right)` intensities
+    fn aligned(&self, aligned: &[(f64, f64, f64)]) -> f64 {
+        match self.metric {
+            Metric::Cosine => cosine(aligned.iter().map(|&(_, left, right)| (left, right))),
+            Metric::WeightedCosine => cosine(self.weighted(aligned)),
+            Metric::Composite => self.composite(aligned),
+            Metric::Entropy => entropy(aligned),
+            Metric::Pearson => pearson(aligned),
+        }
+    }
+
+    fn weighted<'a>(
+        &'a self,
+        aligned: &'a [(f64, f64, f64)],
+    ) -> impl Iterator<Item = (f64, f64)> + 'a {
+        aligned
+            .iter()
+            .map(|&(mass, left, right)| (self.weight(mass, left), self.weight(mass, right)))
+    }
+
+    fn weight(&self, mass: f64, intensity: f64) -> f64 {
+        if intensity == 0.0 {
+            return 0.0;
+        }
+        mass.powf(self.mass_exponent) * intensity.powf(self.intensity_exponent)
+    }
+
+    /// Stein–Scott composite
+    ///
+    /// Squared weighted cosine, corrected by the intensity ratios of adjacent
+    /// common peaks.
+    fn composite(&self, aligned: &[(f64, f64, f64)]) -> f64 {
+        let squared = cosine(self.weighted(aligned)).powi(2);
+        let count = self
+            .weighted(aligned)
+            .filter(|&(left, _)| left > 0.0)
+            .count() as f64;
+        let common = self
+            .weighted(aligned)
+            .filter(|&(left, right)| left > 0.0 && right > 0.0)
+            .collect::<Vec<_>>();
+        if common.len() < 2 {
+            return squared;
+        }
+        let ratios = common
+            .windows(2)
+            .map(|pair| {
+                let ratio = (pair[1].0 / pair[0].0) * (pair[0].1 / pair[1].1);
+                ratio.min(ratio.recip())
+            })
+            .sum::<f64>()
+            / (common.len() - 1) as f64;
+        let common = common.len() as f64;
+        (count * squared + common * ratios) / (count + common)
+    }
+}
+
+/// Intensities binned by nominal mass
+fn binned(peaks: &BTreeMap<N64, f64>) -> Vec<f64> {
+    let mut intensities = Vec::new();
+    for (mass, &intensity) in peaks {
+        let mass = mass.raw().round() as usize;
+        if mass >= intensities.len() {
+            intensities.resize(mass + 1, 0.0);
+        }
+        intensities[mass] += intensity;
+    }
+    intensities
+}
+
+impl Default for Similarity {
+    fn default() -> Self {
+        Self {
+            metric: Metric::Cosine,
+            ..NIST
+        }
+    }
+}
+
+/// Similarity metric
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub enum Metric {
+    /// Cosine of the intensities (`0..=1`)
+    #[default]
+    Cosine,
+    /// Cosine of the weighted intensities (`0..=1`)
+    WeightedCosine,
+    /// Stein–Scott composite (`0..=1`)
+    Composite,
+    /// Spectral entropy similarity (`0..=1`)
+    Entropy,
+    /// Pearson correlation of the intensities (`-1..=1`)
+    Pearson,
+}
+
+impl Display for Metric {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Cosine => f.write_str("Cosine"),
+            Self::WeightedCosine => f.write_str("Weighted cosine"),
+            Self::Composite => f.write_str("Composite"),
+            Self::Entropy => f.write_str("Entropy"),
+            Self::Pearson => f.write_str("Pearson"),
+        }
+    }
+}
+
+fn cosine(pairs: impl Iterator<Item = (f64, f64)>) -> f64 {
+    let (mut product, mut left_norm, mut right_norm) = (0.0, 0.0, 0.0);
+    for (left, right) in pairs {
+        product += left * right;
+        left_norm += left * left;
+        right_norm += right * right;
+    }
+    let norms = (left_norm * right_norm).sqrt();
+    if norms == 0.0 {
+        return 0.0;
+    }
+    product / norms
+}
+
+/// Spectral entropy similarity (Li et al., 2021), unweighted
+fn entropy(aligned: &[(f64, f64, f64)]) -> f64 {
+    let left_sum = aligned.iter().map(|&(_, left, _)| left).sum::<f64>();
+    let right_sum = aligned.iter().map(|&(_, _, right)| right).sum::<f64>();
+    if left_sum == 0.0 || right_sum == 0.0 {
+        return 0.0;
+    }
+    let shannon = |probability: f64| {
+        if probability > 0.0 {
+            -probability * probability.ln()
+        } else {
+            0.0
+        }
+    };
+    let (mut left_entropy, mut right_entropy, mut mixed_entropy) = (0.0, 0.0, 0.0);
+    for &(_, left, right) in aligned {
+        let (left, right) = (left / left_sum, right / right_sum);
+        left_entropy += shannon(left);
+        right_entropy += shannon(right);
+        mixed_entropy += shannon((left + right) / 2.0);
+    }
+    1.0 - (2.0 * mixed_entropy - left_entropy - right_entropy) / 4f64.ln()
+}
+
+fn pearson(aligned: &[(f64, f64, f64)]) -> f64 {
+    let count = aligned.len() as f64;
+    if count == 0.0 {
+        return 0.0;
+    }
+    let left_mean = aligned.iter().map(|&(_, left, _)| left).sum::<f64>() / count;
+    let right_mean = aligned.iter().map(|&(_, _, right)| right).sum::<f64>() / count;
+    cosine(
+        aligned
+            .iter()
+            .map(|&(_, left, right)| (left - left_mean, right - right_mean)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    const METRICS: [Metric; 5] = [
+        Metric::Cosine,
+        Metric::WeightedCosine,
+        Metric::Composite,
+        Metric::Entropy,
+        Metric::Pearson,
+    ];
+
+    fn similarity(metric: Metric) -> Similarity {
+        Similarity {
+            metric,
+            ..Similarity::default()
+        }
+    }
+
+    #[test]
+    fn identical() {
+        let parsed = parse("Name: A\nNum Peaks: 3\n41 50; 43 100; 57 20;\n").unwrap();
+        for metric in METRICS {
+            let similarity = similarity(metric);
+            assert!(
+                (similarity.parsed(&parsed, &parsed) - 1.0).abs() < 1e-9,
+                "{metric}"
+            );
+        }
+    }
+
+    #[test]
+    fn normalized() {
+        let left = parse("Name: A\nNum Peaks: 3\n41 50; 43 100; 57.2 20;\n").unwrap();
+        let right = parse("Name: B\nNum Peaks: 2\n41 999; 43 400;\n").unwrap();
+        let normalized = |parsed: &Parsed| {
+            let max = parsed.peaks.values().max().unwrap().raw();
+            parsed
+                .peaks
+                .iter()
+                .map(|(&mass, intensity)| (mass, 100.0 * intensity.raw() / max))
+                .collect::<BTreeMap<_, _>>()
+        };
+        // Scale invariant
+        for metric in [Metric::Cosine, Metric::Pearson] {
+            let similarity = similarity(metric);
+            let normalized = similarity.normalized(&normalized(&left), &normalized(&right));
+            assert!(
+                (normalized - similarity.parsed(&left, &right)).abs() < 1e-9,
+                "{metric}"
+            );
+        }
+    }
+
+    #[test]
+    fn disjoint() {
+        let left = parse("Name: A\nNum Peaks: 2\n41 50; 43 100;\n").unwrap();
+        let right = parse("Name: B\nNum Peaks: 1\n57 100;\n").unwrap();
+        let empty = parse("Name: C\nNum Peaks: 0\n").unwrap();
+        for metric in METRICS {
+            let similarity = similarity(metric);
+            assert!(similarity.parsed(&left, &right) < 1e-9, "{metric}");
+            assert_eq!(similarity.parsed(&left, &empty), 0.0, "{metric}");
+        }
+    }
+
+    #[test]
+    fn cosine() {
+        let left = parse("Name: A\nNum Peaks: 2\n41 50; 43 100;\n").unwrap();
+        let scaled = parse("Name: B\nNum Peaks: 2\n41 500; 43 1000;\n").unwrap();
+        let right = parse("Name: C\nNum Peaks: 2\n41 100; 43 100;\n").unwrap();
+        let similarity = similarity(Metric::Cosine);
+        assert!((similarity.parsed(&left, &scaled) - 1.0).abs() < 1e-12);
+        let expected = 15000.0 / (12500f64.sqrt() * 20000f64.sqrt());
+        assert!((similarity.parsed(&left, &right) - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn entropy() {
+        let left = [0.0, 1.0, 1.0];
+        let right = [0.0, 1.0, 0.0];
+        let similarity = similarity(Metric::Entropy);
+        // S(A) = ln 2, S(B) = 0, S(AB) = S(3/4, 1/4)
+        let mixed = -(0.75 * 0.75f64.ln() + 0.25 * 0.25f64.ln());
+        let expected = 1.0 - (2.0 * mixed - 2f64.ln()) / 4f64.ln();
+        assert!((similarity.intensities(&left, &right) - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn matrix() {
+        let spectra = [
+            "Name: A\nNum Peaks: 2\n41 50; 43 100;\n",
+            "Name: B\nNum Peaks: 1\n57 100;\n",
+            "Name: C\nNum Peaks: 2\n41 55; 43 100;\n",
+        ]
+        .map(|input| parse(input).unwrap());
+        let matrix = super::matrix(&Similarity::default(), &spectra.each_ref());
+        assert_eq!(matrix.dim(), (3, 3));
+        assert!((matrix[(0, 0)] - 1.0).abs() < 1e-9);
+        assert_eq!(matrix[(0, 1)], matrix[(1, 0)]);
+        let clusters = clusters(&matrix, 0.9);
+        assert_eq!(clusters, [0, 1, 0]);
+        let (order, ordered) = order(matrix.clone(), &clusters);
+        assert_eq!(order, [0, 2, 1]);
+        assert_eq!(ordered[(1, 2)], matrix[(2, 1)]);
+        assert_eq!(ordered[(0, 1)], matrix[(0, 2)]);
+    }
+
+    #[test]
+    fn match_factors() {
+        let library = parse("Name: A\nNum Peaks: 2\n41 50; 43 100;\n").unwrap();
+        let unknown = parse("Name: B\nNum Peaks: 3\n41 50; 43 100; 57 80;\n").unwrap();
+        assert!((match_factor(&library, &library) - 999.0).abs() < 1e-9);
+        assert!(match_factor(&unknown, &library) < 999.0);
+        // The impurity at 57 is ignored
+        assert!((reverse_match_factor(&unknown, &library) - 999.0).abs() < 1e-9);
+        assert!(reverse_match_factor(&library, &unknown) < 999.0);
+    }
+}
@@ -0,0 +1,364 @@
+use crate::parser::Parsed;
+use anyhow::{bail, Error, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{self, Display, Formatter},
+    mem,
+    str::FromStr,
+};
+use uom::si::{f64::Time, time::minute};
+
+/// Filename template (text and `{field}`s matched against the whole stem)
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template(Vec<Token>);
+
+impl Template {
+    /// Fields of the stem, `None` if the template doesn't match it
+    pub fn extract(&self, stem: &str) -> Option<Extracted> {
+        let mut extracted = Extracted::default();
+        matches(&self.0, stem, &mut extracted).then_some(extracted)
+    }
+}
+
+impl FromStr for Template {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let mut tokens = Vec::new();
+        let mut rest = value;
+        while !rest.is_empty() {
+            let Some(start) = rest.find(['{', '}']) else {
+                tokens.push(Token::Literal(rest.to_owned()));
+                break;
+            };
+            if start > 0 {
+                tokens.push(Token::Literal(rest[..start].to_owned()));
+            }
+            if rest[start..].starts_with('}') {
+                bail!("unmatched `}}` at {}", value.len() - rest.len() + start);
+            }
+            let Some(end) = rest[start..].find('}') else {
+                bail!("unclosed `{{` at {}", value.len() - rest.len() + start);
+            };
+            let field = match &rest[start + 1..start + end] {
+                "carbons" => Field::Carbons,
+                "double_bonds" => Field::DoubleBonds,
+                "positions" => Field::Positions,
+                "molecular_ion" => Field::MolecularIon,
+                "retention_time" => Field::RetentionTime,
+                "replicate" => Field::Replicate,
+                "_" => Field::Any,
+                field => bail!("unknown field `{{{field}}}`"),
+            };
+            if let Some(Token::Field(_)) = tokens.last() {
+                bail!("fields without text between them");
+            }
+            tokens.push(Token::Field(field));
+            rest = &rest[start + end + 1..];
+        }
+        Ok(Self(tokens))
+    }
+}
+
+/// Template token
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Literal(String),
+    Field(Field),
+}
+
+/// Template field
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Field {
+    Carbons,
+    DoubleBonds,
+    Positions,
+    MolecularIon,
+    RetentionTime,
+    Replicate,
+    Any,
+}
+
+impl Field {
+    /// Sets the field to the value, `false` if the value doesn't fit it
+    fn set(self, value: &str, extracted: &mut Extracted) -> bool {
+        let digits = |value: &str| !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit());
+        match self {
+            Self::Carbons | Self::DoubleBonds | Self::MolecularIon => {
+                if !digits(value) {
+                    return false;
+                }
+                let Ok(number) = value.parse() else {
+                    return false;
+                };
+                *match self {
+                    Self::Carbons => &mut extracted.carbons,
+                    Self::DoubleBonds => &mut extracted.double_bonds,
+                    _ => &mut extracted.molecular_ion,
+                } = Some(number);
+            }
+            Self::Positions => {
+                if !value.split(',').all(digits) {
+                    return false;
+                }
+                extracted.positions = value.split(',').flat_map(str::parse).collect();
+            }
+            Self::RetentionTime => {
+                let (integer, fraction) = value.split_once(['.', ',']).unwrap_or((value, "0"));
+                if !digits(integer) || !digits(fraction) {
+                    return false;
+                }
+                let Ok(minutes) = format!("{integer}.{fraction}").parse() else {
+                    return false;
+                };
+                extracted.retention_time = Some(Time::new::<minute>(minutes));
+            }
+            Self::Replicate => {
+                if value.trim().is_empty() {
+                    return false;
+                }
+                extracted.replicate = Some(value.trim().to_owned());
+            }
+            Self::Any => {}
+        }
+        true
+    }
+}
+
+/// Fields extracted from a file stem
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Extracted {
+    pub carbons: Option<u64>,
+    pub double_bonds: Option<u64>,
+    /// Double bond positions (Δ, from the carboxyl)
+    pub positions: Vec<u64>,
+    pub molecular_ion: Option<u64>,
+    pub retention_time: Option<Time>,
+    pub replicate: Option<String>,
+}
+
+impl Extracted {
+    /// Lipid shorthand (`Δ9,12 18:2`), if the carbons are known
+    pub fn lipid(&self) -> Option<Lipid> {
+        Some(Lipid {
+            carbons: self.carbons?,
+            double_bonds: self.double_bonds.unwrap_or(self.positions.len() as u64),
+            positions: self.positions.clone(),
+        })
+    }
+
+    /// Sets the fields in the metadata of the spectrum, replacing the ones it
+    /// has (the retention time to the spectrum, unless it has one)
+    pub fn apply(self, parsed: &mut Parsed) {
+        if let Some(lipid) = self.lipid() {
+            parsed.metadata.replace(LIPID, lipid);
+        }
+        if let Some(molecular_ion) = self.molecular_ion {
+            parsed.metadata.replace(MOLECULAR_ION, molecular_ion);
+        }
+        if let Some(replicate) = self.replicate {
+            parsed.metadata.replace(REPLICATE, replicate);
+        }
+        if parsed.retention_time.is_none() {
+            parsed.retention_time = self.retention_time;
+        }
+    }
+}
+
+/// Metadata key of the lipid shorthand
+pub const LIPID: &str = "Lipid";
+
+/// Metadata key of the molecular ion
+pub const MOLECULAR_ION: &str = "Molecular ion";
+
+/// Metadata key of the replicate
+pub const REPLICATE: &str = "Replicate";
+
+/// Lipid shorthand
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Lipid {
+    pub carbons: u64,
+    pub double_bonds: u64,
+    pub positions: Vec<u64>,
+}
+
+impl Display for Lipid {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if !self.positions.is_empty() {
+            write!(
+                f,
+                "Δ{} ",
+                self.positions
+                    .iter()
+                    .map(u64::to_string)
+                    .collect::<Vec<_>>()
+                    .join(",")
+            )?;
+        }
+        write!(f, "{}:{}", self.carbons, self.double_bonds)
+    }
+}
+
+/// Filename templates, saved as written by the user
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(from = "Vec<String>", into = "Vec<String>")]
+pub struct Templates(pub Vec<Entry>);
+
+impl Templates {
+    /// Fields of the stem of the first matching template (invalid ones are
+    /// skipped)
+    pub fn extract(&self, stem: &str) -> Option<Extracted> {
+        self.0
+            .iter()
+            .filter_map(|entry| entry.template.as_ref().ok())
+            .find_map(|template| template.extract(stem))
+    }
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        vec![
+            "{carbons}_{double_bonds}-{molecular_ion} ({retention_time}) #{replicate}".to_owned(),
+            "{carbons}_{double_bonds}-{molecular_ion} ({retention_time})".to_owned(),
+            "{carbons}_{double_bonds}-{molecular_ion}".to_owned(),
+            "{positions}-{carbons}_{double_bonds}".to_owned(),
+            "{positions}_{carbons}_{double_bonds}".to_owned(),
+            "{_} #{replicate}".to_owned(),
+        ]
+        .into()
+    }
+}
+
+impl From<Vec<String>> for Templates {
+    fn from(value: Vec<String>) -> Self {
+        Self(value.into_iter().map(Entry::new).collect())
+    }
+}
+
+impl From<Templates> for Vec<String> {
+    fn from(value: Templates) -> Self {
+        value.0.into_iter().map(|entry| entry.text).collect()
+    }
+}
+
+/// Filename template as written, and its parse (or error)
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub text: String,
+    template: Result<Template, String>,
+}
+
+impl Entry {
+    pub fn new(text: String) -> Self {
+        let template = text.parse().map_err(|error: Error| format!("{error:#}"));
+        Self { text, template }
+    }
+
+    /// Parses the text again, after it's edited
+    pub fn update(&mut self) {
+        *self = Self::new(mem::take(&mut self.text));
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.template.as_ref().err().map(String::as_str)
+    }
+}
+
+/// Matches the tokens against the whole input, trying the shortest values of
+/// the fields first
+fn matches(tokens: &[Token], input: &str, extracted: &mut Extracted) -> bool {
+    let Some((token, tokens)) = tokens.split_first() else {
+        return input.is_empty();
+    };
+    match token {
+        Token::Literal(literal) => input
+            .strip_prefix(&**literal)
+            .is_some_and(|input| matches(tokens, input, extracted)),
+        &Token::Field(field) => {
+            let ends = input
+                .char_indices()
+                .skip(1)
+                .map(|(index, _)| index)
+                .chain([input.len()]);
+            for end in ends {
+                let mut candidate = extracted.clone();
+                if field.set(&input[..end], &mut candidate)
+                    && matches(tokens, &input[end..], &mut candidate)
+                {
+                    *extracted = candidate;
+                    return true;
+                }
+            }
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser, writer::Writer};
+
+    #[test]
+    fn parse() {
+        for entry in Templates::default().0 {
+            assert_eq!(entry.error(), None);
+        }
+        let entry = Entry::new("{mass}".to_owned());
+        assert_eq!(entry.error(), Some("unknown field `{mass}`"));
+        assert!("{carbons".parse::<Template>().is_err());
+        assert!("carbons}".parse::<Template>().is_err());
+        assert!("{mass}".parse::<Template>().is_err());
+        assert!("{carbons}{double_bonds}".parse::<Template>().is_err());
+    }
+
+    #[test]
+    fn extract() {
+        let templates = Templates::default();
+        let extracted = templates.extract("16_1-345 (52,314)").unwrap();
+        assert_eq!(extracted.lipid().unwrap().to_string(), "16:1");
+        assert_eq!(extracted.molecular_ion, Some(345));
+        assert_eq!(extracted.retention_time, Some(Time::new::<minute>(52.314)));
+        let extracted = templates.extract("16_1-345").unwrap();
+        assert_eq!(extracted.retention_time, None);
+        let extracted = templates.extract("9,12-18_2").unwrap();
+        assert_eq!(extracted.lipid().unwrap().to_string(), "Δ9,12 18:2");
+        assert_eq!(extracted.molecular_ion, None);
+        let extracted = templates.extract("9,12_16_2").unwrap();
+        assert_eq!(extracted.positions, [9, 12]);
+        assert_eq!(extracted.carbons, Some(16));
+        assert_eq!(templates.extract("1"), None);
+        let extracted = templates.extract("Blank 2 #3").unwrap();
+        assert_eq!(extracted.replicate.as_deref(), Some("3"));
+        let extracted = templates.extract("16_1-345 (52,314) #2").unwrap();
+        assert_eq!(extracted.molecular_ion, Some(345));
+        assert_eq!(extracted.replicate.as_deref(), Some("2"));
+    }
+
+    #[test]
+    fn apply() {
+        let mut parsed = Parsed::default();
+        Templates::default()
+            .extract("16_1-345 (52,314)")
+            .unwrap()
+            .apply(&mut parsed);
+        assert_eq!(parsed.metadata.get(LIPID), Some("16:1"));
+        assert_eq!(parsed.metadata.get(MOLECULAR_ION), Some("345"));
+        assert_eq!(parsed.retention_time, Some(Time::new::<minute>(52.314)));
+    }
+
+    #[test]
+    fn reapply() {
+        // An exported spectrum dropped again under the same name
+        let extracted = Templates::default()
+            .extract("16_1-345 (52,314) #2")
+            .unwrap();
+        let mut parsed = parser::parse("Name: A\nNum Peaks: 1\n41 100;\n").unwrap();
+        extracted.clone().apply(&mut parsed);
+        let mut parsed = parser::parse(&Writer::default().write(&parsed)).unwrap();
+        extracted.apply(&mut parsed);
+        for (key, value) in [(LIPID, "16:1"), (MOLECULAR_ION, "345"), (REPLICATE, "2")] {
+            assert_eq!(parsed.metadata.get_all(key).collect::<Vec<_>>(), [value]);
+        }
+    }
+}
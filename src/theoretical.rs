@@ -0,0 +1,294 @@
+use crate::{
+    interpretation::{intensity, BASE_IONS},
+    parser::Peaks,
+};
+use anyhow::{bail, ensure, Result};
+use std::fmt::{self, Display, Formatter};
+
+/// Fatty acid
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FattyAcid {
+    /// Carbons, with the ones of the branches
+    pub carbons: u64,
+    /// Double bond positions (Δ, from the carboxyl)
+    pub double_bonds: Vec<u64>,
+    /// Methyl branch positions
+    pub branches: Vec<u64>,
+}
+
+impl FattyAcid {
+    /// Fatty acid of the carbons and the comma separated positions of the
+    /// double bonds and of the branches (e.g. `9,12`)
+    pub fn new(carbons: u64, double_bonds: &str, branches: &str) -> Result<Self> {
+        let double_bonds = positions(double_bonds)?;
+        let branches = positions(branches)?;
+        let main = carbons.saturating_sub(branches.len() as u64);
+        for &position in double_bonds.iter().chain(&branches) {
+            ensure!(
+                (2..main).contains(&position),
+                "position {position} is out of the chain of {main} carbons",
+            );
+        }
+        if let Some(window) = double_bonds
+            .windows(2)
+            .find(|window| window[1] - window[0] < 2)
+        {
+            bail!("double bonds {} and {} are cumulated", window[0], window[1]);
+        }
+        Ok(Self {
+            carbons,
+            double_bonds,
+            branches,
+        })
+    }
+
+    /// Molecular ion of the derivative
+    pub fn molecular_ion(&self, derivative: Derivative) -> u64 {
+        derivative.offset() + 1 + 14 * self.carbons - 2 * self.double_bonds.len() as u64
+    }
+
+    /// Expected diagnostic ions of the derivative, in increasing order
+    pub fn ions(&self, derivative: Derivative) -> Vec<Ion> {
+        let main = self.carbons - self.branches.len() as u64;
+        let molecular_ion = self.molecular_ion(derivative);
+        let mut ions = derivative
+            .base_ions()
+            .iter()
+            .map(|&mass| Ion {
+                mass,
+                kind: Kind::Base,
+            })
+            .collect::<Vec<_>>();
+        for carbons in derivative.first()..main {
+            if derivative == Derivative::Picolinyl && self.double_bonds.contains(&carbons) {
+                continue;
+            }
+            let double_bonds = self
+                .double_bonds
+                .iter()
+                .filter(|&&position| match derivative {
+                    Derivative::Dmox => position <= carbons,
+                    Derivative::Fame | Derivative::Picolinyl => position < carbons,
+                })
+                .count() as u64;
+            let branches = self
+                .branches
+                .iter()
+                .filter(|&&position| position <= carbons)
+                .count() as u64;
+            ions.push(Ion {
+                mass: derivative.offset() + 14 * (carbons + branches) - 2 * double_bonds,
+                kind: Kind::Series(carbons),
+            });
+        }
+        if derivative == Derivative::Fame {
+            ions.push(Ion {
+                mass: molecular_ion - 31,
+                kind: Kind::Loss(31),
+            });
+        }
+        ions.push(Ion {
+            mass: molecular_ion,
+            kind: Kind::Molecular,
+        });
+        ions.sort_by_key(|ion| ion.mass);
+        ions.dedup_by_key(|ion| ion.mass);
+        ions
+    }
+}
+
+impl Display for FattyAcid {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let join = |positions: &[u64]| {
+            positions
+                .iter()
+                .map(u64::to_string)
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        if !self.branches.is_empty() {
+            write!(f, "{}-Me-", join(&self.branches))?;
+        }
+        if !self.double_bonds.is_empty() {
+            write!(f, "Δ{} ", join(&self.double_bonds))?;
+        }
+        write!(f, "{}:{}", self.carbons, self.double_bonds.len())
+    }
+}
+
+/// Fatty acid derivative
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Derivative {
+    /// Picolinyl (3-pyridylcarbinyl) ester
+    #[default]
+    Picolinyl,
+    /// 4,4-dimethyloxazoline
+    Dmox,
+    /// Fatty acid methyl ester
+    Fame,
+}
+
+impl Derivative {
+    fn base_ions(self) -> &'static [u64] {
+        match self {
+            Self::Picolinyl => &BASE_IONS,
+            Self::Dmox => &[113, 126],
+            Self::Fame => &[74, 87],
+        }
+    }
+
+    /// Carbons of the first fragment of the series
+    fn first(self) -> u64 {
+        match self {
+            Self::Picolinyl | Self::Dmox => 3,
+            Self::Fame => 2,
+        }
+    }
+
+    /// Mass of the fragment of the series without carbons
+    fn offset(self) -> u64 {
+        match self {
+            Self::Picolinyl => 122,
+            Self::Dmox => 84,
+            Self::Fame => 45,
+        }
+    }
+}
+
+impl Display for Derivative {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Picolinyl => f.write_str("Picolinyl"),
+            Self::Dmox => f.write_str("DMOX"),
+            Self::Fame => f.write_str("FAME"),
+        }
+    }
+}
+
+/// Expected ion
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ion {
+    pub mass: u64,
+    pub kind: Kind,
+}
+
+/// Kind of the expected ion
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Base,
+    /// Fragment of the carbons
+    Series(u64),
+    /// Molecular ion less the mass
+    Loss(u64),
+    Molecular,
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Base => f.write_str("Base"),
+            Self::Series(carbons) => write!(f, "C{carbons}"),
+            Self::Loss(mass) => write!(f, "M-{mass}"),
+            Self::Molecular => f.write_str("M"),
+        }
+    }
+}
+
+/// Comparison of the expected ions with a spectrum
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Comparison {
+    pub matched: Vec<Ion>,
+    pub missing: Vec<Ion>,
+}
+
+/// Compares the expected ions with the peaks, matching the ones at least as
+/// intense as the threshold relative to the base peak
+pub fn compare(ions: &[Ion], peaks: &Peaks, threshold: f64) -> Comparison {
+    let base = peaks.values().max().map_or(0.0, |max| max.raw());
+    let (matched, missing) = ions.iter().partition(|ion| {
+        let intensity = intensity(peaks, ion.mass);
+        intensity > 0.0 && intensity >= threshold * base
+    });
+    Comparison { matched, missing }
+}
+
+fn positions(input: &str) -> Result<Vec<u64>> {
+    let mut positions = input
+        .split(',')
+        .map(str::trim)
+        .filter(|position| !position.is_empty())
+        .map(|position| Ok(position.parse()?))
+        .collect::<Result<Vec<u64>>>()?;
+    positions.sort();
+    positions.dedup();
+    Ok(positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+    use std::{fs::read_to_string, path::Path};
+
+    #[test]
+    fn fatty_acid() {
+        let fatty_acid = FattyAcid::new(18, "12, 9", "").unwrap();
+        assert_eq!(fatty_acid.double_bonds, [9, 12]);
+        assert_eq!(fatty_acid.to_string(), "Δ9,12 18:2");
+        assert_eq!(fatty_acid.molecular_ion(Derivative::Picolinyl), 371);
+        assert_eq!(fatty_acid.molecular_ion(Derivative::Dmox), 333);
+        assert_eq!(fatty_acid.molecular_ion(Derivative::Fame), 294);
+        assert!(FattyAcid::new(18, "9,10", "").is_err());
+        assert!(FattyAcid::new(18, "18", "").is_err());
+        assert!(FattyAcid::new(18, "x", "").is_err());
+    }
+
+    #[test]
+    fn ions() {
+        let fatty_acid = FattyAcid::new(18, "9,12", "").unwrap();
+        let masses = |derivative| {
+            fatty_acid
+                .ions(derivative)
+                .iter()
+                .map(|ion| ion.mass)
+                .collect::<Vec<_>>()
+        };
+        let picolinyl = masses(Derivative::Picolinyl);
+        assert!(picolinyl.starts_with(&[92, 108, 151, 164, 178]));
+        // The vinylic ions (248, 286) are left out
+        assert!(picolinyl.contains(&234) && picolinyl.contains(&260));
+        assert!(!picolinyl.contains(&248) && !picolinyl.contains(&286));
+        assert!(picolinyl.ends_with(&[356, 371]));
+        // 12 amu gaps from the ions of C8 and C11
+        let dmox = masses(Derivative::Dmox);
+        assert!(dmox.contains(&196) && dmox.contains(&208));
+        assert!(dmox.contains(&236) && dmox.contains(&248));
+        let branched = FattyAcid::new(17, "", "14").unwrap();
+        let picolinyl = branched
+            .ions(Derivative::Picolinyl)
+            .iter()
+            .map(|ion| ion.mass)
+            .collect::<Vec<_>>();
+        // 28 amu across the branch
+        assert!(picolinyl.ends_with(&[304, 332, 346, 361]));
+    }
+
+    #[test]
+    fn comparison() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("input/1/9,12-18_2.MSP");
+        let parsed = parse(&read_to_string(path).unwrap()).unwrap();
+        let ions = FattyAcid::new(18, "9,12", "")
+            .unwrap()
+            .ions(Derivative::Picolinyl);
+        let comparison = compare(&ions, &parsed.peaks, 0.01);
+        assert_eq!(
+            comparison.matched.len() + comparison.missing.len(),
+            ions.len()
+        );
+        assert!(comparison.missing.len() < ions.len() / 4);
+        let ions = FattyAcid::new(18, "", "")
+            .unwrap()
+            .ions(Derivative::Picolinyl);
+        assert!(compare(&ions, &parsed.peaks, 0.01).missing.len() > comparison.missing.len());
+    }
+}
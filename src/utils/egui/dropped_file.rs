@@ -1,20 +1,30 @@
 use anyhow::{bail, Result};
 use egui::DroppedFile;
-use std::fs::read_to_string;
+use std::{borrow::Cow, fs::read, path::Path};
 
 /// Extension methods for [`DroppedFile`]
 pub trait DroppedFileExt {
-    fn content(&self) -> Result<String>;
+    fn bytes(&self) -> Result<Cow<[u8]>>;
+
+    /// File name without its extension
+    fn stem(&self) -> Option<&str>;
 }
 
 impl DroppedFileExt for DroppedFile {
-    fn content(&self) -> Result<String> {
+    fn bytes(&self) -> Result<Cow<[u8]>> {
         Ok(match &self.bytes {
-            Some(bytes) => String::from_utf8(bytes.to_vec())?,
+            Some(bytes) => Cow::Borrowed(bytes),
             None => match &self.path {
-                Some(path) => read_to_string(&path)?,
+                Some(path) => Cow::Owned(read(path)?),
                 None => bail!("Dropped file hasn't bytes or path"),
             },
         })
     }
+
+    fn stem(&self) -> Option<&str> {
+        match &self.path {
+            Some(path) => path.file_stem()?.to_str(),
+            None => Path::new(&self.name).file_stem()?.to_str(),
+        }
+    }
 }
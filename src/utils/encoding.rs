@@ -0,0 +1,114 @@
+use anyhow::{bail, Result};
+use chardetng::EncodingDetector;
+use encoding_rs::{
+    Encoding, IBM866, KOI8_R, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1251, WINDOWS_1252,
+};
+use std::str;
+
+/// Encodings offered for manual selection
+pub const ENCODINGS: [&Encoding; 7] = [
+    UTF_8,
+    UTF_16LE,
+    UTF_16BE,
+    WINDOWS_1251,
+    WINDOWS_1252,
+    KOI8_R,
+    IBM866,
+];
+
+/// Decodes the bytes
+///
+/// Without an explicit encoding the BOM is used if present, otherwise the
+/// encoding is detected. Returns the text and the encoding actually used.
+pub fn decode(
+    bytes: &[u8],
+    encoding: Option<&'static Encoding>,
+) -> Result<(String, &'static Encoding)> {
+    let (encoding, bytes) = match (encoding, Encoding::for_bom(bytes)) {
+        (Some(encoding), Some((bom, length))) if encoding == bom => (encoding, &bytes[length..]),
+        (Some(encoding), _) => (encoding, bytes),
+        (None, Some((bom, length))) => (bom, &bytes[length..]),
+        (None, None) => (detect(bytes), bytes),
+    };
+    match encoding.decode_without_bom_handling_and_without_replacement(bytes) {
+        Some(text) => Ok((text.into_owned(), encoding)),
+        None => bail!("Invalid {} text", encoding.name()),
+    }
+}
+
+/// Detects the encoding of the bytes without BOM
+fn detect(bytes: &[u8]) -> &'static Encoding {
+    // UTF-16 text in Latin script has a zero in every other byte
+    let zeros = |skip| {
+        bytes
+            .iter()
+            .skip(skip)
+            .step_by(2)
+            .filter(|&&byte| byte == 0)
+            .count()
+    };
+    let (even, odd) = (zeros(0), zeros(1));
+    let quarter = bytes.len() / 4;
+    if odd > quarter && even < odd / 8 {
+        return UTF_16LE;
+    }
+    if even > quarter && odd < even / 8 {
+        return UTF_16BE;
+    }
+    if str::from_utf8(bytes).is_ok() {
+        return UTF_8;
+    }
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, true);
+    detector.guess(None, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "Name: Компонент at scan 2946 (52.313 min)\r\nNum Peaks: 1\r\n92 999;\r\n";
+
+    #[test]
+    fn utf_8() {
+        assert_eq!(
+            decode(TEXT.as_bytes(), None).unwrap(),
+            (TEXT.to_owned(), UTF_8)
+        );
+        let bytes = [b"\xEF\xBB\xBF", TEXT.as_bytes()].concat();
+        assert_eq!(decode(&bytes, None).unwrap(), (TEXT.to_owned(), UTF_8));
+    }
+
+    #[test]
+    fn utf_16() {
+        let le = TEXT
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect::<Vec<_>>();
+        let be = TEXT
+            .encode_utf16()
+            .flat_map(u16::to_be_bytes)
+            .collect::<Vec<_>>();
+        assert_eq!(decode(&le, None).unwrap(), (TEXT.to_owned(), UTF_16LE));
+        assert_eq!(decode(&be, None).unwrap(), (TEXT.to_owned(), UTF_16BE));
+        let bytes = [&b"\xFF\xFE"[..], &le].concat();
+        assert_eq!(decode(&bytes, None).unwrap(), (TEXT.to_owned(), UTF_16LE));
+    }
+
+    #[test]
+    fn windows() {
+        let (bytes, _, _) = WINDOWS_1251.encode(TEXT);
+        assert_eq!(
+            decode(&bytes, None).unwrap(),
+            (TEXT.to_owned(), WINDOWS_1251)
+        );
+        let (text, encoding) = decode(&bytes, Some(WINDOWS_1252)).unwrap();
+        assert_ne!(text, TEXT);
+        assert_eq!(encoding, WINDOWS_1252);
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(decode(b"Name: \xFF\n", Some(UTF_8)).is_err());
+    }
+}
@@ -1,9 +1,14 @@
 pub(crate) use self::{
     bound::{BoundExt, RangeBoundsExt},
     display::Trait as Display,
-    egui::{CollapsingStateExt, DroppedFileExt, InnerResponseExt, ResponseExt, UiExt},
+    egui::{
+        CollapsingStateExt, DroppedFileExt, InnerResponseExt, ResponseExt, SelectableValueFromIter,
+        UiExt,
+    },
+    encoding::{decode, ENCODINGS},
     float::FloatExt,
     higher_order_functions::with_index,
+    ndarray::SortArray,
     stats::Stats,
 };
 
@@ -12,8 +17,8 @@ pub mod stats;
 mod bound;
 mod display;
 mod egui;
+mod encoding;
 mod float;
 mod higher_order_functions;
+mod ndarray;
 mod string;
-mod temp;
-// mod ndarray;
@@ -3,14 +3,14 @@ use rawpointer::PointerExt;
 use std::{cmp::Ordering, ptr::copy_nonoverlapping};
 
 /// Sort array
-pub trait SortArray: PermuteArray {
-    fn sort_axis_by<F>(self, axis: Axis, cmp: F) -> Array<Self::Item, Self::Dim>
+pub trait SortArray: Sized {
+    fn sort_axis_by<F>(self, axis: Axis, cmp: F) -> Self
     where
         F: FnMut(&usize, &usize) -> Ordering;
 }
 
 impl<A, D: Dimension + RemoveAxis> SortArray for Array<A, D> {
-    fn sort_axis_by<F>(self, axis: Axis, cmp: F) -> Array<Self::Item, Self::Dim>
+    fn sort_axis_by<F>(self, axis: Axis, cmp: F) -> Self
     where
         F: FnMut(&usize, &usize) -> Ordering,
     {
@@ -80,18 +80,6 @@ struct Permutation {
     indices: Vec<usize>,
 }
 
-impl Permutation {
-    fn new(indices: Vec<usize>) -> Self {
-        Self {
-            indices: indices.into_iter().collect(),
-        }
-    }
-
-    fn sort_by<F: FnMut(&usize, &usize) -> Ordering>(&mut self, cmp: F) {
-        self.indices.sort_by(cmp);
-    }
-}
-
 impl FromIterator<usize> for Permutation {
     fn from_iter<T: IntoIterator<Item = usize>>(iter: T) -> Self {
         Self {
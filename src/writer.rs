@@ -0,0 +1,266 @@
+use crate::parser::Parsed;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter, Write};
+
+/// MSP writer
+///
+/// With the default scaling the output parses back to an identical
+/// [`Parsed`].
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Writer {
+    /// Peaks per line (`0` writes all peaks in one line)
+    pub peaks_per_line: usize,
+    pub separator: Separator,
+    pub scaling: Scaling,
+}
+
+impl Writer {
+    pub fn write(&self, parsed: &Parsed) -> String {
+        let mut output = String::new();
+        self.write_record(&mut output, parsed).ok();
+        output
+    }
+
+    /// Writes records separated by blank lines
+    pub fn write_library<'a>(&self, library: impl IntoIterator<Item = &'a Parsed>) -> String {
+        library
+            .into_iter()
+            .map(|parsed| self.write(parsed))
+            .join("\n")
+    }
+
+    fn write_record(&self, output: &mut String, parsed: &Parsed) -> fmt::Result {
+        writeln!(output, "Name: {}", parsed.name)?;
+        if !parsed.comments.is_empty() {
+            writeln!(output, "Comments: {}", parsed.comments)?;
+        }
+        if let Some(cas) = &parsed.cas {
+            writeln!(output, "CAS#: {cas}")?;
+        }
+        if let Some(db) = parsed.db {
+            writeln!(output, "DB#: {db}")?;
+        }
+        if !parsed.formula.is_empty() {
+            writeln!(output, "Formula: {}", parsed.formula)?;
+        }
+        if let Some(mw) = parsed.mw {
+            writeln!(output, "MW: {mw}")?;
+        }
+        if let Some(nist) = parsed.nist {
+            writeln!(output, "NIST#: {nist}")?;
+        }
+        if !parsed.synonym.is_empty() {
+            writeln!(output, "Synonym: {}", parsed.synonym)?;
+        }
+        for (key, value) in parsed.metadata.iter() {
+            writeln!(output, "{key}: {value}")?;
+        }
+        let max = parsed.peaks.values().max().map_or(0.0, |max| max.raw());
+        // Peaks of zero intensity only are left as they are
+        let scale = |value: f64| match self.scaling {
+            Scaling::BasePeak(base) if max > 0.0 => value / max * base,
+            _ => value,
+        };
+        if !parsed.deviations.is_empty() {
+            output.push_str("Deviations:");
+            for (index, (mass, deviation)) in parsed.deviations.iter().enumerate() {
+                let separator = if index == 0 { " " } else { "; " };
+                write!(output, "{separator}{mass} {}", scale(deviation.raw()))?;
+            }
+            output.push('\n');
+        }
+        writeln!(output, "Num Peaks: {}", parsed.peaks.len())?;
+        let peaks = parsed.peaks.iter().collect::<Vec<_>>();
+        let size = match self.peaks_per_line {
+            0 => peaks.len().max(1),
+            size => size,
+        };
+        for line in peaks.chunks(size) {
+            for (mass, intensity) in line {
+                let intensity = match self.scaling {
+                    Scaling::None => intensity.raw(),
+                    Scaling::BasePeak(_) => scale(intensity.raw()).round(),
+                };
+                let (inner, outer) = self.separator.pair();
+                write!(output, "{mass}{inner}{intensity}")?;
+                if let Some(annotation) = parsed.annotations.get(mass) {
+                    let annotation = annotation.replace('\\', "\\\\").replace('"', "\\\"");
+                    write!(output, " \"{annotation}\"")?;
+                }
+                output.push_str(outer);
+            }
+            output.push('\n');
+        }
+        Ok(())
+    }
+}
+
+impl Default for Writer {
+    fn default() -> Self {
+        Self {
+            peaks_per_line: 5,
+            separator: Separator::Semicolon,
+            scaling: Scaling::None,
+        }
+    }
+}
+
+/// Separator style
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub enum Separator {
+    /// `27 28; 29 39; ` (NIST)
+    #[default]
+    Semicolon,
+    /// `27:28 29:39 ` (AMDIS)
+    Colon,
+    /// `27\t28\t29\t39\t`
+    Tab,
+}
+
+impl Separator {
+    /// Separators inside and after a pair
+    fn pair(&self) -> (&'static str, &'static str) {
+        match self {
+            Self::Semicolon => (" ", "; "),
+            Self::Colon => (":", " "),
+            Self::Tab => ("\t", "\t"),
+        }
+    }
+}
+
+impl Display for Separator {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Semicolon => f.write_str("Semicolon"),
+            Self::Colon => f.write_str("Colon"),
+            Self::Tab => f.write_str("Tab"),
+        }
+    }
+}
+
+/// Intensity scaling
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub enum Scaling {
+    /// Intensities as they are (lossless)
+    #[default]
+    None,
+    /// Intensities scaled to the base peak and rounded (`999.0` for NIST)
+    BasePeak(f64),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        operations::average,
+        parser::{parse, parse_library, tests::files},
+    };
+    use noisy_float::types::n64;
+    use std::{fs::read_to_string, path::Path};
+
+    #[test]
+    fn round_trip() {
+        for path in files(&Path::new(env!("CARGO_MANIFEST_DIR")).join("input")) {
+            let input = read_to_string(&path).unwrap();
+            let library = parse_library(&input)
+                .into_iter()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            for writer in [
+                Writer::default(),
+                Writer {
+                    peaks_per_line: 0,
+                    separator: Separator::Colon,
+                    ..Writer::default()
+                },
+                Writer {
+                    peaks_per_line: 1,
+                    separator: Separator::Tab,
+                    ..Writer::default()
+                },
+            ] {
+                for parsed in &library {
+                    assert_eq!(&parse(&writer.write(parsed)).unwrap(), parsed);
+                }
+                let output = writer.write_library(&library);
+                let parsed = parse_library(&output)
+                    .into_iter()
+                    .collect::<Result<Vec<_>, _>>()
+                    .unwrap();
+                assert_eq!(parsed, library, "{}", path.display());
+            }
+        }
+    }
+
+    #[test]
+    fn metadata() {
+        let input = "Name: Unknown\nComments: some\nMW: 345\nPrecursorMZ: 303.2324\nNum Peaks: 2\n121.0648 2345.7; 122 12;\n";
+        let parsed = parse(input).unwrap();
+        assert_eq!(
+            Writer::default().write(&parsed),
+            input.replace(";\n", "; \n")
+        );
+    }
+
+    #[test]
+    fn annotations() {
+        let parsed = parse("Name: X\nNum Peaks: 2\n65 100\n91 999 \"C7H7+\"\n").unwrap();
+        let output = Writer::default().write(&parsed);
+        assert_eq!(
+            output,
+            "Name: X\nNum Peaks: 2\n65 100; 91 999 \"C7H7+\"; \n"
+        );
+        assert_eq!(parse(&output).unwrap(), parsed);
+    }
+
+    #[test]
+    fn repeated() {
+        let input = "Name: X\nSynonym: A\nSynonym: B\nNum Peaks: 1\n41 50; \n";
+        let parsed = parse(input).unwrap();
+        assert_eq!(Writer::default().write(&parsed), input);
+    }
+
+    #[test]
+    fn escaped() {
+        let mut parsed = parse("Name: X\nNum Peaks: 1\n91 999\n").unwrap();
+        parsed
+            .annotations
+            .insert(n64(91.0), r#"C7H7+ "tropylium" \ 1"#.to_owned());
+        let output = Writer::default().write(&parsed);
+        assert_eq!(
+            output,
+            "Name: X\nNum Peaks: 1\n91 999 \"C7H7+ \\\"tropylium\\\" \\\\ 1\"; \n"
+        );
+        assert_eq!(parse(&output).unwrap(), parsed);
+    }
+
+    #[test]
+    fn deviations() {
+        let first = parse("Name: A\nNum Peaks: 2\n41 50; 43 100;\n").unwrap();
+        let second = parse("Name: B\nNum Peaks: 2\n41 100; 43 100;\n").unwrap();
+        let parsed = average(&[&first, &second], 0.5).unwrap();
+        let output = Writer::default().write(&parsed);
+        assert!(output.contains("\nDeviations: 41 35.355339059327"));
+        assert!(output.contains("; 43 0\nNum Peaks: 2\n"));
+        assert_eq!(parse(&output).unwrap(), parsed);
+    }
+
+    #[test]
+    fn scaling() {
+        let parsed = parse("Name: X\nNum Peaks: 2\n41 50; 43 200;\n").unwrap();
+        let writer = Writer {
+            scaling: Scaling::BasePeak(999.0),
+            ..Writer::default()
+        };
+        assert_eq!(
+            writer.write(&parsed),
+            "Name: X\nNum Peaks: 2\n41 250; 43 999; \n",
+        );
+        let parsed = parse("Name: X\nNum Peaks: 2\n41 0; 43 0;\n").unwrap();
+        assert_eq!(
+            writer.write(&parsed),
+            "Name: X\nNum Peaks: 2\n41 0; 43 0; \n"
+        );
+    }
+}